@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use fnv::{FnvHashMap, FnvHashSet};
+use crate::{ Point, Cell, Action, Orientation, Scorer, Level, Drone, UNDECIDED_ZONE, step, explore_wrapping };
+use crate::strategy::Planner;
+
+// States kept after every step, steps looked ahead, and steps taken before looking again
+const WIDTH:   usize = 12;
+const HORIZON: usize = 8;
+const COMMIT:  usize = 3;
+// Distance to the closest cell left to wrap is only looked up this far from the drone
+const RADIUS:  isize = 24;
+
+// A drone some steps into the future: what it did to the level is kept
+// aside, so that the level itself is shared by every state
+#[derive(Clone)]
+pub(crate) struct Sim {
+    pub(crate) plan:        Vec<Action>,
+    pub(crate) pos:         Point,
+    pub(crate) orientation: Orientation,
+    pub(crate) wheels:      usize,
+    pub(crate) drill:       usize,
+    pub(crate) wrapped:     FnvHashSet<Point>,
+    pub(crate) drilled:     FnvHashSet<Point>,
+    pub(crate) collected:   Vec<Point>,  // booster cells picked up
+    pub(crate) gained:      f64,         // wrapped cells and boosters, rated by the scorer
+    pub(crate) first:       usize        // steps until something was gained, 0 if nothing yet
+}
+
+impl Sim {
+    pub(crate) fn new(drone: &Drone) -> Sim {
+        Sim { plan: Vec::new(), pos: drone.pos, orientation: drone.orientation, wheels: drone.wheels, drill: drone.drill,
+              wrapped: FnvHashSet::default(), drilled: FnvHashSet::default(), collected: Vec::new(), gained: 0., first: 0 }
+    }
+
+    // State after one more action, None if it can't be taken
+    pub(crate) fn next(&self, level: &Level, drone: &Drone, scorer: &Scorer, action: Action) -> Option<Sim> {
+        let (pos, orientation, wrapped, drilled) = step(level, Some(drone), &self.pos, self.orientation, &action, self.wheels > 0, self.drill > 0, &self.drilled)?;
+        let mut sim = self.clone();
+        sim.plan.push(action);
+        sim.pos = pos;
+        sim.orientation = orientation;
+        sim.wheels = self.wheels.saturating_sub(1);
+        sim.drill = self.drill.saturating_sub(1);
+        for p in wrapped {
+            if sim.wrapped.insert(p) { sim.gained += worth(level, drone, scorer, &p); }
+        }
+        sim.drilled.extend(drilled);
+        if let Some(&bonus) = level.bonuses.get(&pos) {
+            if !sim.collected.contains(&pos) {
+                sim.collected.push(pos);
+                sim.gained += scorer.booster(bonus);
+            }
+        }
+        if sim.first == 0 && sim.gained > self.gained { sim.first = sim.plan.len(); }
+        Some(sim)
+    }
+}
+
+// Rating of a wrapped cell, cells outside of the drone's zone count as scorer.zone
+fn worth(level: &Level, drone: &Drone, scorer: &Scorer, p: &Point) -> f64 {
+    let rating = 1.0_f64.max(scorer.wall * level.weights[level.grid_idx(p.x, p.y)] as f64);
+    if drone.zone != UNDECIDED_ZONE && level.get_zone(p.x, p.y) != drone.zone { rating * scorer.zone } else { rating }
+}
+
+// Steps to the closest cell of the drone's zone left to wrap, for walkable cells
+// around the drone. Further away or unreachable within the square is RADIUS
+fn distances(level: &Level, drone: &Drone) -> FnvHashMap<Point, isize> {
+    let around = |p: &Point| (p.x - drone.pos.x).abs() <= RADIUS && (p.y - drone.pos.y).abs() <= RADIUS;
+    let mut dist: FnvHashMap<Point, isize> = FnvHashMap::default();
+    let mut queue: VecDeque<Point> = VecDeque::new();
+    for y in (drone.pos.y - RADIUS).max(0)..(drone.pos.y + RADIUS + 1).min(level.height) {
+        for x in (drone.pos.x - RADIUS).max(0)..(drone.pos.x + RADIUS + 1).min(level.width) {
+            if level.get_cell(x, y) == Cell::EMPTY && (drone.zone == UNDECIDED_ZONE || level.get_zone(x, y) == drone.zone) {
+                dist.insert(Point::new(x, y), 0);
+                queue.push_back(Point::new(x, y));
+            }
+        }
+    }
+    while let Some(p) = queue.pop_front() {
+        let d = dist[&p];
+        for (dx, dy) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
+            let n = Point::new(p.x + dx, p.y + dy);
+            if around(&n) && level.walkable(n.x, n.y) && !dist.contains_key(&n) {
+                dist.insert(n, d + 1);
+                queue.push_back(n);
+            }
+        }
+    }
+    dist
+}
+
+// Keeps the WIDTH best states HORIZON steps ahead, rated by what they wrapped and collected
+// minus how far they end up from what's left to wrap, one state per position and orientation
+pub(crate) fn search(level: &Level, drone: &Drone, scorer: &Scorer) -> Option<Sim> {
+    let dist = distances(level, drone);
+    let rank = |sim: &Sim| sim.gained - *dist.get(&sim.pos).unwrap_or(&RADIUS) as f64;
+    let mut beam = vec![Sim::new(drone)];
+    for _ in 0..HORIZON {
+        let mut next: FnvHashMap<(Point, Orientation), Sim> = FnvHashMap::default();
+        for sim in &beam {
+            for action in &[Action::LEFT, Action::RIGHT, Action::UP, Action::DOWN, Action::CLOCKWISE, Action::COUNTERCLOCKWISE, Action::JUMP0, Action::JUMP1, Action::JUMP2] {
+                if let Some(sim2) = sim.next(level, drone, scorer, *action) {
+                    let key = (sim2.pos, sim2.orientation);
+                    if next.get(&key).is_none_or(|other| rank(other) < rank(&sim2)) { next.insert(key, sim2); }
+                }
+            }
+        }
+        if next.is_empty() { break; }
+        beam = next.into_values().collect();
+        // ties broken by plan, so that runs don't depend on hashing order
+        beam.sort_by(|a, b| rank(b).partial_cmp(&rank(a)).unwrap().then_with(|| order(&a.plan).cmp(&order(&b.plan))));
+        beam.truncate(WIDTH);
+    }
+    beam.into_iter().next().filter(|sim| sim.gained > 0.)
+}
+
+fn order(plan: &[Action]) -> Vec<usize> {
+    plan.iter().map(|a| *a as usize).collect()
+}
+
+// Takes the first COMMIT steps of the best state found by the beam search, or more
+// until it gains something, so that drones can't go back and forth. Wraps greedily
+// when nothing is within reach of the horizon
+pub(crate) struct BeamPlanner;
+
+impl Planner for BeamPlanner {
+    fn plan(&mut self, level: &Level, drones: &[Drone], idx: usize, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+        let drone = &drones[idx];
+        match search(level, drone, scorer) {
+            Some(sim) => Some(sim.plan.into_iter().take(COMMIT.max(sim.first)).collect()),
+            None      => explore_wrapping(level, drone, scorer, seed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_level;
+
+    #[test]
+    fn beam_search() {
+        // a 3-wide corridor: the drone wraps it all by going right, never counting a cell twice
+        let problem = parse_level("(0,0),(12,0),(12,3),(0,3)#(0,1)##").unwrap();
+        let (level, drone) = (&problem.level, &problem.drones[0]);
+        let sim = search(level, drone, &Scorer::default()).unwrap();
+        assert_eq!(sim.plan, vec![Action::RIGHT; HORIZON]);
+        assert_eq!(sim.pos, Point::new(HORIZON as isize, 1));
+        assert!(sim.gained >= 3. * HORIZON as f64);
+        assert_eq!(sim.first, 1);
+
+        // going back over wrapped cells gains nothing
+        let back = sim.next(level, drone, &Scorer::default(), Action::LEFT).unwrap();
+        assert_eq!((back.gained, back.wrapped.len()), (sim.gained, sim.wrapped.len()));
+        assert!(sim.next(level, drone, &Scorer::default(), Action::DOWN).is_some());
+        assert!(Sim::new(drone).next(level, drone, &Scorer::default(), Action::LEFT).is_none());
+    }
+}
@@ -0,0 +1,58 @@
+use std::cmp::min;
+use fnv::FnvHashMap;
+
+// Contest points for a problem: ceil(weight * best time / our time), where
+// the best time includes ours, so a problem never gives more than its weight
+pub fn points(weight: f64, best: usize, steps: usize) -> f64 {
+    (weight * min(best, steps) as f64 / steps as f64).ceil()
+}
+
+// prob-001 for path/to/prob-001.desc, prob-001.sol or prob-001
+pub fn problem_key(name: &str) -> &str {
+    let name = &name[name.rfind('/').map_or(0, |i| i + 1)..];
+    name.strip_suffix(".desc").or_else(|| name.strip_suffix(".sol")).unwrap_or(name)
+}
+
+// Best known times, a problem and its steps per line separated by comma or spaces:
+//   prob-001,48
+//   prob-002.desc 120
+// Empty lines, # comments and a header line are skipped
+pub fn parse_best(s: &str) -> Result<FnvHashMap<String, usize>, String> {
+    let mut best = FnvHashMap::default();
+    for (i, line) in s.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() { continue; }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+        match (fields.as_slice(), fields.get(1).map(|s| s.parse::<usize>())) {
+            ([problem, _], Some(Ok(steps))) if steps > 0 => { best.insert(problem_key(problem).to_string(), steps); }
+            ([_, _], Some(Err(_))) if i == 0 => {}
+            _ => return Err(format!("line {}: expected problem and steps, got {:?}", i + 1, line))
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn best_times() {
+        let best = parse_best("problem,steps\nprob-001,48\n\n# ours\nproblems/prob-002.desc   120\n").unwrap();
+        assert_eq!(best.len(), 2);
+        assert_eq!(best["prob-001"], 48);
+        assert_eq!(best["prob-002"], 120);
+        assert!(parse_best("prob-001,48\nprob-002").is_err());
+        assert!(parse_best("prob-001,48\nprob-002,x").is_err());
+        assert!(parse_best("prob-001,0").is_err());
+    }
+
+    #[test]
+    fn contest_points() {
+        let weight = crate::shop::weight(8, 4);
+        assert_eq!(weight, 5000.0);
+        assert_eq!(points(weight, 100, 100), 5000.0);
+        assert_eq!(points(weight, 100, 300), 1667.0);
+        assert_eq!(points(weight, 100, 50), 5000.0);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::Write;
+use crate::{ Point, Cell, Bonus, Problem, Solution, UNDECIDED_ZONE, zone_char };
+use crate::validator::{ Replay, Invalid };
+
+// Everything a solution did, step by step
+struct Trace {
+    replay:  Replay,                   // state after the last step
+    paths:   Vec<(usize, Vec<Point>)>, // spawn step and position after each step, per drone
+    beakons: Vec<(Point, usize)>,
+    error:   Option<Invalid>
+}
+
+fn trace(problem: &Problem, solution: &Solution) -> Trace {
+    let mut replay = Replay::new(problem.level.clone(), problem.drones.clone(), solution);
+    let mut paths: Vec<(usize, Vec<Point>)> = replay.drones.iter().map(|d| (0, vec![d.pos])).collect();
+    let mut beakons = Vec::new();
+    let mut error = None;
+    while !replay.done() {
+        if let Err(invalid) = replay.advance() {
+            error = Some(invalid);
+            break;
+        }
+        for (idx, drone) in replay.drones.iter().enumerate() {
+            if idx < paths.len() {
+                paths[idx].1.push(drone.pos);
+            } else {
+                paths.push((replay.step, vec![drone.pos]));
+            }
+        }
+        for &b in &replay.level.beakons[beakons.len()..] {
+            beakons.push((b, replay.step));
+        }
+    }
+    Trace { replay, paths, beakons, error }
+}
+
+fn bonus_char(bonus: Bonus) -> char {
+    match bonus {
+        Bonus::HAND     => 'B',
+        Bonus::WHEELS   => 'F',
+        Bonus::DRILL    => 'L',
+        Bonus::TELEPORT => 'R',
+        Bonus::CLONE    => 'C'
+    }
+}
+
+fn zone_color(zone: u8) -> String {
+    if zone == UNDECIDED_ZONE { String::from("#d0d0d0") }
+    else { format!("hsl({:.0},40%,82%)", (zone as f64 * 137.5) % 360.0) }
+}
+
+fn drone_color(idx: usize) -> String {
+    format!("hsl({:.0},80%,40%)", (idx as f64 * 97.0 + 10.0) % 360.0)
+}
+
+// Pixels per cell, so that the picture is about 800 pixels wide
+fn cell_size(problem: &Problem) -> isize {
+    (800 / problem.level.width.max(problem.level.height)).clamp(2, 40)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Static picture: walls, zones, boosters, spawns, beakons and drone trajectories.
+// y axis points up as in the problem
+pub fn svg(problem: &Problem, solution: &Solution) -> String {
+    let trace = trace(problem, solution);
+    let level = &problem.level;
+    let (w, h, c) = (level.width, level.height, cell_size(problem));
+    let mut out = String::new();
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", w * c, h * c + 20, w, h as f64 + 20.0 / c as f64).unwrap();
+    writeln!(out, "<g transform=\"matrix(1 0 0 -1 0 {})\" shape-rendering=\"crispEdges\">", h).unwrap();
+    // cells, a rect per run of the same color in a row
+    for y in 0..h {
+        let mut x = 0;
+        while x < w {
+            let color = |x| if level.get_cell(x, y) == Cell::BLOCKED { String::from("#404040") } else { zone_color(level.get_zone(x, y)) };
+            let fill = color(x);
+            let from = x;
+            while x < w && color(x) == fill { x += 1; }
+            writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>", from, y, x - from, fill).unwrap();
+        }
+    }
+    for &(p, _) in &trace.replay.drilled {
+        writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#a08060\"/>", p.x, p.y).unwrap();
+    }
+    writeln!(out, "</g>").unwrap();
+
+    // items and paths in flipped coordinates, so that text isn't upside down
+    let cx = |p: &Point| p.x as f64 + 0.5;
+    let cy = |p: &Point| (h - p.y) as f64 - 0.5;
+    for (p, bonus) in &level.bonuses {
+        writeln!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"0.45\" fill=\"#2070e0\"><title>{} ({},{})</title></circle>", cx(p), cy(p), bonus_char(*bonus), p.x, p.y).unwrap();
+    }
+    for p in &level.spawns {
+        writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"0.9\" height=\"0.9\" fill=\"#10a040\"><title>X ({},{})</title></rect>", cx(p) - 0.45, cy(p) - 0.45, p.x, p.y).unwrap();
+    }
+    for (idx, (spawn, path)) in trace.paths.iter().enumerate() {
+        let mut points = String::new();
+        for (i, p) in path.iter().enumerate() {
+            let jump = i > 0 && (p.x - path[i - 1].x).abs() + (p.y - path[i - 1].y).abs() > 2;
+            write!(points, "{}{:.1},{:.1} ", if i == 0 || jump { "M" } else { "L" }, cx(p), cy(p)).unwrap();
+        }
+        writeln!(out, "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"0.25\" stroke-opacity=\"0.8\"><title>drone {} from step {}</title></path>", points.trim_end(), drone_color(idx), idx, spawn).unwrap();
+    }
+    for (p, step) in &trace.beakons {
+        writeln!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"0.6\" fill=\"none\" stroke=\"#e02020\" stroke-width=\"0.2\"><title>beakon ({},{}) from step {}</title></circle>", cx(p), cy(p), p.x, p.y, step).unwrap();
+    }
+    let status = match &trace.error {
+        Some(invalid) => format!("invalid: {}", invalid),
+        None          => format!("{} steps, {} drones", trace.replay.step, trace.paths.len())
+    };
+    writeln!(out, "<text x=\"0.2\" y=\"{}\" font-size=\"{}\" font-family=\"monospace\">{}</text>", h as f64 + 14.0 / c as f64, 12.0 / c as f64, escape(&status)).unwrap();
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+fn json_points(points: &[(Point, usize)]) -> String {
+    let items: Vec<String> = points.iter().map(|(p, step)| format!("[{},{},{}]", p.x, p.y, step)).collect();
+    format!("[{}]", items.join(","))
+}
+
+// Canvas drawing with a time slider: cells get wrapped, boosters disappear,
+// beakons appear and drones move as the slider goes
+pub fn html(problem: &Problem, solution: &Solution) -> String {
+    let trace = trace(problem, solution);
+    let level = &problem.level;
+    let (w, h) = (level.width, level.height);
+    let cells: String = (0..h).rev().flat_map(|y| (0..w).map(move |x| (x, y))).map(|(x, y)| {
+        if level.get_cell(x, y) == Cell::BLOCKED { '#' }
+        else { zone_char(level.get_zone(x, y)) }
+    }).collect();
+    let bonuses: Vec<String> = level.bonuses.iter().map(|(p, bonus)| {
+        let step = trace.replay.picked_up.iter().find(|(q, _)| q == p).map_or(-1, |&(_, s)| s as isize);
+        format!("[{},{},\"{}\",{}]", p.x, p.y, bonus_char(*bonus), step)
+    }).collect();
+    let spawns: Vec<String> = level.spawns.iter().map(|p| format!("[{},{}]", p.x, p.y)).collect();
+    let paths: Vec<String> = trace.paths.iter().map(|(spawn, path)| {
+        let points: Vec<String> = path.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+        format!("[{},[{}]]", spawn, points.join(","))
+    }).collect();
+    let status = match &trace.error {
+        Some(invalid) => format!("invalid: {}", invalid),
+        None          => format!("{} steps, {} drones", trace.replay.step, trace.paths.len())
+    };
+    // last one for cells outside of any zone
+    let zones = (0..level.zones_empty.len()).map(|z| z as u8).chain(Some(UNDECIDED_ZONE))
+                    .map(|z| format!("\"{}\"", zone_color(z))).collect::<Vec<_>>().join(",");
+
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title></head><body style=\"font-family: monospace\">", escape(&status)).unwrap();
+    writeln!(out, "<div><input id=\"step\" type=\"range\" min=\"0\" max=\"{}\" value=\"{}\" style=\"width: 800px\"> step <span id=\"label\"></span> &nbsp; {}</div>", trace.replay.step, trace.replay.step, escape(&status)).unwrap();
+    writeln!(out, "<canvas id=\"map\"></canvas>\n<script>").unwrap();
+    writeln!(out, "const W = {}, H = {}, C = {};", w, h, cell_size(problem)).unwrap();
+    writeln!(out, "const cells = \"{}\";", cells).unwrap();
+    writeln!(out, "const zones = [{}];", zones).unwrap();
+    writeln!(out, "const wrapped = {};", json_points(&trace.replay.wrapped)).unwrap();
+    writeln!(out, "const drilled = {};", json_points(&trace.replay.drilled)).unwrap();
+    writeln!(out, "const beakons = {};", json_points(&trace.beakons)).unwrap();
+    writeln!(out, "const bonuses = [{}];", bonuses.join(",")).unwrap();
+    writeln!(out, "const spawns = [{}];", spawns.join(",")).unwrap();
+    writeln!(out, "const paths = [{}];", paths.join(",")).unwrap();
+    out.push_str(HTML_SCRIPT);
+    writeln!(out, "</script>\n</body></html>").unwrap();
+    out
+}
+
+const HTML_SCRIPT: &str = r##"const canvas = document.getElementById("map");
+canvas.width = W * C; canvas.height = H * C;
+const ctx = canvas.getContext("2d");
+const slider = document.getElementById("step");
+const px = (x) => x * C, py = (y) => (H - 1 - y) * C;
+const zone = (code) => code < 0xC0 ? code - 65 : code - 0xC0 + 26;
+function cell(x, y, color) { ctx.fillStyle = color; ctx.fillRect(px(x), py(y), C, C); }
+function draw() {
+  const t = +slider.value;
+  document.getElementById("label").textContent = t;
+  for (let y = 0; y < H; y++)
+    for (let x = 0; x < W; x++) {
+      const c = cells[(H - 1 - y) * W + x];
+      cell(x, y, c === "#" ? "#404040" : zones[c === "-" ? zones.length - 1 : zone(c.charCodeAt(0))]);
+    }
+  for (const [x, y, s] of drilled) if (s <= t) cell(x, y, "#a08060");
+  for (const [x, y, s] of wrapped) if (s <= t) cell(x, y, "#f0d040");
+  for (const [x, y] of spawns) cell(x, y, "#10a040");
+  for (const [x, y, b, s] of bonuses) if (s < 0 || s > t) cell(x, y, "#2070e0");
+  for (const [x, y, s] of beakons) if (s <= t) cell(x, y, "#e02020");
+  paths.forEach(([spawn, path], idx) => {
+    if (spawn > t) return;
+    const end = Math.min(path.length / 2 - 1, t - spawn);
+    ctx.strokeStyle = ctx.fillStyle = "hsl(" + ((idx * 97 + 10) % 360) + ",80%,40%)";
+    ctx.lineWidth = Math.max(1, C / 4);
+    ctx.beginPath();
+    for (let i = 0; i <= end; i++) {
+      const x = px(path[2 * i]) + C / 2, y = py(path[2 * i + 1]) + C / 2;
+      const jump = i > 0 && Math.abs(path[2 * i] - path[2 * i - 2]) + Math.abs(path[2 * i + 1] - path[2 * i - 1]) > 2;
+      if (i === 0 || jump) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+    }
+    ctx.stroke();
+    ctx.fillRect(px(path[2 * end]) - C / 2, py(path[2 * end + 1]) - C / 2, 2 * C, 2 * C);
+  });
+}
+slider.addEventListener("input", draw);
+draw();
+"##;
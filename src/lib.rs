@@ -0,0 +1,935 @@
+#![allow(clippy::upper_case_acronyms, clippy::too_many_arguments, clippy::if_same_then_else)]
+
+mod parser;
+mod validator;
+mod solution;
+pub mod puzzle;
+pub mod viewer;
+pub mod export;
+pub mod shop;
+pub mod results;
+pub mod contest;
+mod strategy;
+mod tour;
+mod sweep;
+mod beam;
+mod mcts;
+pub mod scorer;
+mod optimize;
+
+pub use parser::{ParseError, Fragment, Reason};
+pub use validator::Invalid;
+pub use solution::{Solution, SolAction, SolutionError, Timing, parse_solution};
+pub use strategy::STRATEGIES;
+pub use scorer::{Scorer, parse_scorer};
+pub use optimize::optimize;
+
+use std::{fmt, thread, time};
+use std::cell::RefCell;
+use std::cmp::{min, max};
+use std::collections::{VecDeque};
+use std::hash::{Hash, Hasher};
+use fnv::{FnvHashMap, FnvHashSet, FnvHasher};
+use lazy_static::lazy_static;
+use strategy::Decision;
+
+const DELAY: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point { pub x: isize, pub y: isize }
+
+impl Point {
+    pub fn new(x: isize, y: isize) -> Point { Point{ x, y } }
+}
+
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_i16(self.x as i16);
+        state.write_i16(self.y as i16);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell { EMPTY, BLOCKED, WRAPPED }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action { UP, RIGHT, DOWN, LEFT, JUMP0, JUMP1, JUMP2, CLOCKWISE, COUNTERCLOCKWISE }
+
+// Where the drone's initial arm (x == 1 in drone coordinates) points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Orientation { RIGHT, DOWN, LEFT, UP }
+
+impl Orientation {
+    fn clockwise(self) -> Orientation {
+        match self { Orientation::RIGHT => Orientation::DOWN, Orientation::DOWN => Orientation::LEFT, Orientation::LEFT => Orientation::UP, Orientation::UP => Orientation::RIGHT }
+    }
+
+    fn counterclockwise(self) -> Orientation {
+        match self { Orientation::RIGHT => Orientation::UP, Orientation::UP => Orientation::LEFT, Orientation::LEFT => Orientation::DOWN, Orientation::DOWN => Orientation::RIGHT }
+    }
+
+    // drone coordinates -> level offset
+    fn rotate(self, p: &Point) -> Point {
+        match self {
+            Orientation::RIGHT => Point::new( p.x,  p.y),
+            Orientation::DOWN  => Point::new( p.y, -p.x),
+            Orientation::LEFT  => Point::new(-p.x, -p.y),
+            Orientation::UP    => Point::new(-p.y,  p.x)
+        }
+    }
+
+    // level offset -> drone coordinates
+    fn unrotate(self, p: &Point) -> Point {
+        match self {
+            Orientation::RIGHT => Point::new( p.x,  p.y),
+            Orientation::DOWN  => Point::new(-p.y,  p.x),
+            Orientation::LEFT  => Point::new(-p.x, -p.y),
+            Orientation::UP    => Point::new( p.y, -p.x)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bonus { HAND, WHEELS, DRILL, TELEPORT, CLONE }
+
+fn get_or<K>(m: &FnvHashMap<K, usize>, k: &K, default: usize) -> usize
+    where K: std::hash::Hash + Eq + std::marker::Sized
+{
+    if let Some(v) = m.get(k) { *v } else { default }
+}
+
+fn update<K>(m: &mut FnvHashMap<K, usize>, k: K, delta: isize)
+    where K: std::hash::Hash + Eq + std::marker::Sized
+{
+    let old_v: usize = get_or(m, &k, 0);
+    let new_v = old_v as isize + delta;
+    if new_v > 0 { m.insert(k, new_v as usize); }
+    else { m.remove(&k); }
+}
+
+fn less(a: (i64, i64), b: (i64, i64)) -> bool {
+    a.0 * b.1 < b.0 * a.1
+}
+
+// Interval of t (as fractions with positive denominators) on which the segment
+// from the center of cell 0 to the center of cell h is strictly inside cell c,
+// all along one axis
+fn crossing(h: isize, c: isize) -> ((i64, i64), (i64, i64)) {
+    let (h, c) = (h as i64, c as i64);
+    if h == 0 {
+        if c == 0 { ((-1, 1), (2, 1)) } else { ((0, 1), (0, 1)) }
+    } else if h > 0 {
+        ((2 * c - 1, 2 * h), (2 * c + 1, 2 * h))
+    } else {
+        ((-2 * c - 1, -2 * h), (1 - 2 * c, -2 * h))
+    }
+}
+
+// Cells that must be free for a hand at offset to reach: the ones whose
+// interior is crossed by the segment between cell centers, hand itself included
+fn hand_blockers(hand: &Point) -> Vec<Point> {
+    let mut res = Vec::new();
+    for cx in min(0, hand.x)..=max(0, hand.x) {
+        for cy in min(0, hand.y)..=max(0, hand.y) {
+            if cx == 0 && cy == 0 { continue; }
+            let (x_lo, x_hi) = crossing(hand.x, cx);
+            let (y_lo, y_hi) = crossing(hand.y, cy);
+            let lo = if less(x_lo, y_lo) { y_lo } else { x_lo };
+            let hi = if less(x_hi, y_hi) { x_hi } else { y_hi };
+            if less(lo, hi) { res.push(Point::new(cx, cy)); }
+        }
+    }
+    res
+}
+
+// Hands at most this far from the body along both axes have their blockers precomputed
+const REACH: isize = 24;
+
+lazy_static! {
+    // hand_blockers of offsets within REACH, row by row from (-REACH, -REACH)
+    static ref HAND_BLOCKERS: Vec<Vec<Point>> = (-REACH..=REACH)
+        .flat_map(|y| (-REACH..=REACH).map(move |x| hand_blockers(&Point::new(x, y))))
+        .collect();
+}
+
+thread_local! {
+    // hand_blockers of offsets beyond REACH, computed the first time a hand that long shows up
+    static FAR_HAND_BLOCKERS: RefCell<FnvHashMap<Point, Vec<Point>>> = RefCell::new(FnvHashMap::default());
+}
+
+// Where activate_hand puts the next manipulator, in drone coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attach {
+    LINE,             // (1, y+1) above the topmost hand
+    SYMMETRIC,        // alternately above and below the x == 1 column
+    TSHAPE,           // alternately widens the x == 1 column and grows a stem at y == 0 behind the body
+    LOOKAHEAD(usize)  // the adjacent cell wrapping most along the next N greedy steps
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub interactive: bool,
+    pub attach:      Attach,
+    pub strategy:    &'static str, // one of STRATEGIES
+    pub scorer:      Scorer,
+    pub seed:        u64,          // 0 is the canonical run, others reseed zones and break ties differently
+    pub budget:      Option<time::Duration> // time for the whole problem, for strategies that search
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { interactive: false, attach: Attach::LINE, strategy: STRATEGIES[0], scorer: Scorer::default(), seed: 0, budget: None }
+    }
+}
+
+// Same spelling as the command line flags
+impl fmt::Display for Attach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Attach::LINE         => write!(f, "line"),
+            Attach::SYMMETRIC    => write!(f, "symmetric"),
+            Attach::TSHAPE       => write!(f, "tshape"),
+            Attach::LOOKAHEAD(n) => write!(f, "lookahead:{}", n)
+        }
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "hands={}", self.attach)?;
+        if self.scorer != Scorer::default() { write!(f, " {}", self.scorer)?; }
+        if self.seed != 0 { write!(f, " seed={}", self.seed)?; }
+        Ok(())
+    }
+}
+
+type Zone = u8;
+const UNDECIDED_ZONE: Zone = !0;
+// Zone ids below UNDECIDED_ZONE, one of them kept spare
+const MAX_ZONES: usize = 254;
+// 'A' to 'Z', then on from 'À', clear of quotes and backslashes
+fn zone_char(zone: Zone) -> char {
+    if zone == UNDECIDED_ZONE { '-' }
+    else if zone < 26 { char::from_u32('A' as u32 + zone as u32).unwrap_or('-') }
+    else { char::from_u32('À' as u32 + zone as u32 - 26).unwrap_or('-') }
+}
+
+#[derive(Clone)]
+pub struct Drone {
+    pos:    Point,
+    hands:  Vec<Point>,
+    orientation: Orientation,
+    wheels: usize,
+    drill:  usize,
+    path:   Vec<SolAction>,
+    plan:   VecDeque<Action>,
+    zone:   Zone
+}
+
+impl Drone {
+    fn new(pos: Point) -> Drone {
+        Drone { pos,
+                hands:  vec![Point::new(0,0), Point::new(1,-1), Point::new(1,0), Point::new(1,1)],
+                orientation: Orientation::RIGHT,
+                wheels: 0,
+                drill:  0,
+                path:   Vec::new(),
+                plan:   VecDeque::new(),
+                zone:   UNDECIDED_ZONE}
+    }
+
+    fn wrap_bot(&self, level: &mut Level) {
+        let mut to_wrap: FnvHashSet<Point> = FnvHashSet::default();
+        would_wrap(level, self, &self.pos, self.orientation, &mut to_wrap);
+        for p in to_wrap {
+            level.wrap_cell(p.x, p.y);
+        }
+    }
+
+    fn collect(&self, level: &mut Level) {
+        if let Some(bonus) = level.bonuses.get(&self.pos) {
+            if let Some(collected) = level.collected.get_mut(bonus) {
+                *collected += 1;
+            } else {
+                level.collected.insert(*bonus, 1);
+            }
+            level.bonuses.remove(&self.pos);
+        }
+    }
+
+    fn wear_off(&mut self) {
+        if self.wheels > 0 { self.wheels -= 1; }
+        if self.drill > 0 { self.drill -= 1; }
+    }
+
+    // The next action of the plan can still be taken. Wheels go further than planned
+    // once another drone drills the cell a plan stopped short of
+    fn can_follow(&self, level: &Level) -> bool {
+        self.plan.front().is_some_and(|a| step(level, None, &self.pos, self.orientation, a, self.wheels > 0, self.drill > 0, &FnvHashSet::default()).is_some())
+    }
+
+    // At least n free cells in a line in some direction
+    fn has_space(&self, level: &Level, n: isize) -> bool {
+        (1..=n).all(   |i| level.valid(self.pos.x, self.pos.y+i) && level.get_cell(self.pos.x, self.pos.y+i) != Cell::BLOCKED)
+        || (1..=n).all(|i| level.valid(self.pos.x, self.pos.y-i) && level.get_cell(self.pos.x, self.pos.y-i) != Cell::BLOCKED)
+        || (1..=n).all(|i| level.valid(self.pos.x+i, self.pos.y) && level.get_cell(self.pos.x+i, self.pos.y) != Cell::BLOCKED)
+        || (1..=n).all(|i| level.valid(self.pos.x-i, self.pos.y) && level.get_cell(self.pos.x-i, self.pos.y) != Cell::BLOCKED)
+    }
+
+    fn column(&self) -> (isize, isize) {
+        let ys = self.hands.iter().filter(|h| h.x == 1).map(|h| h.y);
+        (ys.clone().min().unwrap(), ys.max().unwrap())
+    }
+
+    fn stem(&self) -> isize {
+        self.hands.iter().filter(|h| h.y == 0).map(|h| h.x).min().unwrap()
+    }
+
+    // Free cells next to the body or a hand, in drone coordinates
+    fn attachable(&self) -> Vec<Point> {
+        let mut res: Vec<Point> = Vec::new();
+        for hand in &self.hands {
+            for (dx, dy) in &[(0,1),(0,-1),(-1,0),(1,0)] {
+                let p = Point::new(hand.x + dx, hand.y + dy);
+                if !self.hands.contains(&p) && !res.contains(&p) { res.push(p); }
+            }
+        }
+        res
+    }
+
+    // Positions of the next `steps` greedy moves and the level as it would be left after them
+    fn lookahead(&self, level: &Level, steps: usize, scorer: &Scorer) -> (Vec<(Point, Orientation)>, Level) {
+        let mut level = level.clone();
+        let mut drone = self.clone();
+        let mut trail = vec![(drone.pos, drone.orientation)];
+        while trail.len() <= steps && level.empty > 0 {
+            if let Some(plan) = explore_wrapping(&level, &drone, scorer, 0) {
+                for action in plan.iter().take(steps + 1 - trail.len()) {
+                    drone.act(action, &mut level);
+                    trail.push((drone.pos, drone.orientation));
+                }
+            } else { break; }
+        }
+        (trail, level)
+    }
+
+    fn attach_point(&self, level: &Level, attach: Attach, scorer: &Scorer) -> Point {
+        let (bottom, top) = self.column();
+        match attach {
+            Attach::LINE => Point::new(1, top + 1),
+            Attach::SYMMETRIC => if top <= -bottom { Point::new(1, top + 1) } else { Point::new(1, bottom - 1) },
+            Attach::TSHAPE => {
+                let stem = -self.stem();
+                if stem < (top - bottom) / 2 { Point::new(-stem - 1, 0) }
+                else { self.attach_point(level, Attach::SYMMETRIC, scorer) }
+            }
+            Attach::LOOKAHEAD(steps) => {
+                let (trail, after) = self.lookahead(level, steps, scorer);
+                let mut best = (self.attach_point(level, Attach::SYMMETRIC, scorer), 0);
+                for hand in self.attachable() {
+                    let mut wrapped: FnvHashSet<Point> = FnvHashSet::default();
+                    for (pos, orientation) in &trail {
+                        let offset = orientation.rotate(&hand);
+                        let p = Point::new(pos.x + offset.x, pos.y + offset.y);
+                        if after.valid(p.x, p.y) && after.get_cell(p.x, p.y) == Cell::EMPTY && is_reaching(level, pos, &offset) {
+                            wrapped.insert(p);
+                        }
+                    }
+                    if wrapped.len() > best.1 { best = (hand, wrapped.len()); }
+                }
+                best.0
+            }
+        }
+    }
+
+    // Applies a decision of the strategy, returns the new drone if it was cloned
+    fn execute(&mut self, decision: Decision, level: &mut Level) -> Option<Drone> {
+        match decision {
+            Decision::CLONE => {
+                update(&mut level.collected, Bonus::CLONE, -1);
+                self.path.push(SolAction::CLONE);
+                return Some(Drone::new(self.pos));
+            }
+            Decision::WHEELS => {
+                update(&mut level.collected, Bonus::WHEELS, -1);
+                self.wheels = 51;
+                self.path.push(SolAction::WHEELS);
+            }
+            Decision::DRILL => {
+                update(&mut level.collected, Bonus::DRILL, -1);
+                self.drill = 31;
+                self.path.push(SolAction::DRILL);
+            }
+            Decision::HAND(hand) => {
+                debug_assert!(self.attachable().contains(&hand));
+                update(&mut level.collected, Bonus::HAND, -1);
+                self.path.push(SolAction::HAND(self.orientation.rotate(&hand)));
+                self.hands.push(hand);
+            }
+            Decision::BEAKON => {
+                update(&mut level.collected, Bonus::TELEPORT, -1);
+                self.path.push(SolAction::BEAKON);
+                level.beakons.push(self.pos);
+            }
+            Decision::PLAN(zone, plan) => {
+                self.zone = zone;
+                self.plan = plan;
+                self.follow(level);
+            }
+            Decision::FOLLOW => self.follow(level)
+        }
+        None
+    }
+
+    fn follow(&mut self, level: &mut Level) {
+        if let Some(action) = self.plan.pop_front() {
+            self.act(&action, level);
+        } else if self.wheels > 0 {
+            self.path.push(SolAction::WAIT);
+        } else {
+            panic!("Nothing to do");
+        }
+    }
+
+    fn act(&mut self, action: &Action, level: &mut Level) {
+        let wheels = self.wheels > 0;
+        let drill = self.drill > 0;
+        if let Some((pos, orientation, new_wrapped, new_drilled)) = step(level, Some(self), &self.pos, self.orientation, action, wheels, drill, &FnvHashSet::default()) {
+            self.pos = pos;
+            self.orientation = orientation;
+            self.path.push(match action {
+                Action::UP    => SolAction::UP,
+                Action::DOWN  => SolAction::DOWN,
+                Action::LEFT  => SolAction::LEFT,
+                Action::RIGHT => SolAction::RIGHT,
+                Action::JUMP0 => SolAction::JUMP(level.beakons[0]),
+                Action::JUMP1 => SolAction::JUMP(level.beakons[1]),
+                Action::JUMP2 => SolAction::JUMP(level.beakons[2]),
+                Action::CLOCKWISE        => SolAction::CLOCKWISE,
+                Action::COUNTERCLOCKWISE => SolAction::COUNTERCLOCKWISE
+            });
+            for p in new_wrapped {
+                level.wrap_cell(p.x, p.y);
+            }
+            for p in new_drilled {
+                level.drill_cell(p.x, p.y);
+            }
+        } else {
+            panic!("Unwalkable from ({},{}) {:?}", self.pos.x, self.pos.y, action);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Level {
+    grid:        Vec<Cell>,
+    inside:      Vec<bool>,
+    weights:     Vec<u8>,
+    zones:       Vec<Zone>,
+    width:       isize,
+    height:      isize,
+    empty:       usize,
+    zones_empty: Vec<usize>,
+    spawns:      FnvHashSet<Point>,
+    beakons:     Vec<Point>,
+    bonuses:     FnvHashMap<Point, Bonus>,
+    collected:   FnvHashMap<Bonus, usize>
+}
+
+impl Level {
+    fn grid_idx(&self, x: isize, y: isize) -> usize {
+        (x + y * self.width) as usize
+    }
+
+    fn get_cell(&self, x: isize, y: isize) -> Cell {
+        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
+        self.grid[self.grid_idx(x, y)]
+    }
+
+    fn get_zone(&self, x: isize, y: isize) -> u8 {
+        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
+        self.zones[self.grid_idx(x, y)]
+    }
+
+    fn wrap_cell(&mut self, x: isize, y: isize) {
+        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
+        debug_assert!(self.get_cell(x, y) == Cell::EMPTY);
+        let idx = self.grid_idx(x, y);
+        self.empty -= 1;
+        let zone = self.zones[idx];
+        if zone < 255 {
+            self.zones_empty[zone as usize] -= 1;
+        }
+        self.grid[idx] = Cell::WRAPPED;
+    }
+
+    fn drill_cell(&mut self, x: isize, y: isize) {
+        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
+        debug_assert!(self.get_cell(x, y) == Cell::BLOCKED);
+        let idx = self.grid_idx(x, y);
+        self.grid[idx] = Cell::WRAPPED;
+    }
+
+    fn valid(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
+
+    fn in_map(&self, x: isize, y: isize) -> bool {
+        self.valid(x, y) && self.inside[self.grid_idx(x, y)]
+    }
+
+    fn walkable(&self, x: isize, y: isize) -> bool {
+        self.valid(x, y) && self.get_cell(x, y) != Cell::BLOCKED
+    }
+}
+
+fn reaches(level: &Level, drone: &Drone, hand: &Point, x: isize, y: isize) -> bool {
+    let offset = drone.orientation.rotate(hand);
+    drone.pos.x + offset.x == x && drone.pos.y + offset.y == y && is_reaching(level, &drone.pos, &offset)
+}
+
+// Viewport of 100x50 cells around drones[follow]
+fn print_level(level: &Level, drones: &[Drone], follow: usize) {
+    let center = drones[follow].pos;
+    let ymin = max(0, min(center.y - 25, level.height - 50));
+    let ymax = min(max(center.y + 25, 50), level.height);
+    let xmin = max(0, min(center.x - 50, level.width - 100));
+    let xmax = min(max(center.x + 50, 100), level.width);
+
+    for y in (ymin..ymax).rev() {
+        for x in xmin..xmax {
+            let point = Point::new(x, y);
+
+            let bg = if drones.iter().find(|d| d.hands.iter().find(|h| {
+                       reaches(level, d, h, x, y)
+                     }).is_some()).is_some() { "\x1B[48;5;202m" }
+            else if level.bonuses.contains_key(&point) { "\x1B[48;5;33m\x1B[38;5;15m" }
+            else if level.spawns.contains(&point)       { "\x1B[48;5;33m\x1B[38;5;15m" }
+            else if level.beakons.contains(&point)      { "\x1B[48;5;33m\x1B[38;5;15m" }
+            else {
+                match level.get_cell(x, y) {
+                    Cell::EMPTY   => { "\x1B[48;5;252m" }
+                    Cell::BLOCKED => { "\x1B[48;5;240m" }
+                    Cell::WRAPPED => { "\x1B[48;5;227m" }
+                }
+            };
+
+            let char = if let Some((idx, _)) = drones.iter().enumerate().find(|(_, d)| d.hands.iter().find(|h| {
+                       reaches(level, d, h, x, y)
+                     }).is_some()) { idx.to_string() }
+            else if let Some(bonus) = level.bonuses.get(&point) {
+                    String::from(match bonus {
+                        Bonus::HAND     => { "B" }
+                        Bonus::WHEELS   => { "F" }
+                        Bonus::DRILL    => { "L" }
+                        Bonus::TELEPORT => { "R" }
+                        Bonus::CLONE    => { "C" }
+                    })
+                } else if level.spawns.contains(&point) {
+                    String::from("X")
+                } else if let Some(beakon_idx) = level.beakons.iter().position(|&x| x == point) {
+                    beakon_idx.to_string()
+                } else {
+                    zone_char(level.get_zone(x, y)).to_string()
+                };
+            print!("{}{}\x1B[0m", bg, char);
+        }
+        println!()
+    }
+    println!()
+}
+
+struct Plan {
+    plan:    VecDeque<Action>,
+    pos:     Point,
+    orientation: Orientation,
+    wheels:  usize,
+    drill:   usize,
+    drilled: FnvHashSet<Point>
+}
+
+// hand is an offset on the level, i.e. already rotated
+fn is_reaching(level: &Level, from: &Point, hand: &Point) -> bool {
+    let free = |blockers: &[Point]| blockers.iter().all(|p| level.walkable(from.x+p.x, from.y+p.y));
+    if hand.x.abs() <= REACH && hand.y.abs() <= REACH {
+        free(&HAND_BLOCKERS[((hand.y + REACH) * (2 * REACH + 1) + hand.x + REACH) as usize])
+    } else {
+        FAR_HAND_BLOCKERS.with(|far| free(far.borrow_mut().entry(*hand).or_insert_with(|| hand_blockers(hand))))
+    }
+}
+
+fn would_wrap(level: &Level, drone: &Drone, pos: &Point, orientation: Orientation, wrapped: &mut FnvHashSet<Point>) {
+    for hand in &drone.hands {
+        let offset = orientation.rotate(hand);
+        if is_reaching(level, pos, &offset) {
+            let hand_pos = Point::new(pos.x + offset.x, pos.y + offset.y);
+            if level.get_cell(hand_pos.x, hand_pos.y) == Cell::EMPTY {
+                wrapped.insert(hand_pos);
+            }
+        }
+    }
+}
+
+fn step_move(level: &Level, drone: Option<&Drone>, from: &Point, orientation: Orientation, dx: isize, dy: isize, wheels: bool, drill: bool, drilled: &FnvHashSet<Point>) -> Option<(Point, Orientation, FnvHashSet<Point>, FnvHashSet<Point>)>
+{
+    let mut to = Point::new(from.x + dx, from.y + dy);
+    let mut new_wrapped = FnvHashSet::default();
+    let mut new_drilled = FnvHashSet::default();
+    if drilled.contains(&to) || (drill && level.in_map(to.x, to.y)) || level.walkable(to.x, to.y) {
+        if let Some(drone) = drone { would_wrap(level, drone, &to, orientation, &mut new_wrapped); }
+        if drill && !drilled.contains(&to) && !level.walkable(to.x, to.y) {
+            new_drilled.insert(to);
+        }
+        if wheels {
+            let to2 = Point::new(to.x + dx, to.y + dy);
+            if drilled.contains(&to2) || (drill && level.in_map(to2.x, to2.y)) || level.walkable(to2.x, to2.y) {
+                if let Some(drone) = drone { would_wrap(level, drone, &to2, orientation, &mut new_wrapped); }
+                if drill && !drilled.contains(&to2) && level.valid(to2.x, to2.y) && !level.walkable(to2.x, to2.y) {
+                    new_drilled.insert(to2);
+                }
+                to = to2;
+            }
+        }
+        Some((to, orientation, new_wrapped, new_drilled))
+    } else {
+        None
+    }
+}
+
+fn step_jump(level: &Level, drone: Option<&Drone>, orientation: Orientation, beakon_idx: usize) -> Option<(Point, Orientation, FnvHashSet<Point>, FnvHashSet<Point>)>
+{
+    if beakon_idx < level.beakons.len() {
+        let to = level.beakons[beakon_idx];
+        let mut new_wrapped = FnvHashSet::default();
+        if let Some(drone) = drone { would_wrap(level, drone, &to, orientation, &mut new_wrapped); }
+        Some((to, orientation, new_wrapped, FnvHashSet::default()))
+    } else {
+        None
+    }
+}
+
+fn step_turn(level: &Level, drone: Option<&Drone>, from: &Point, orientation: Orientation) -> Option<(Point, Orientation, FnvHashSet<Point>, FnvHashSet<Point>)>
+{
+    let mut new_wrapped = FnvHashSet::default();
+    if let Some(drone) = drone { would_wrap(level, drone, from, orientation, &mut new_wrapped); }
+    Some((*from, orientation, new_wrapped, FnvHashSet::default()))
+}
+
+// Where action takes a drone and what it wraps with drone's hands and drills on the way.
+// Without a drone nothing is wrapped, for searches that only need where it ends up
+fn step(level: &Level, drone: Option<&Drone>, from: &Point, orientation: Orientation, action: &Action, wheels: bool, drill: bool, drilled: &FnvHashSet<Point>) -> Option<(Point, Orientation, FnvHashSet<Point>, FnvHashSet<Point>)> {
+    match action {
+        Action::LEFT  => step_move(level, drone, from, orientation, -1,  0, wheels, drill, drilled),
+        Action::RIGHT => step_move(level, drone, from, orientation,  1,  0, wheels, drill, drilled),
+        Action::UP    => step_move(level, drone, from, orientation,  0,  1, wheels, drill, drilled),
+        Action::DOWN  => step_move(level, drone, from, orientation,  0, -1, wheels, drill, drilled),
+        Action::JUMP0 => step_jump(level, drone, orientation, 0),
+        Action::JUMP1 => step_jump(level, drone, orientation, 1),
+        Action::JUMP2 => step_jump(level, drone, orientation, 2),
+        Action::CLOCKWISE        => step_turn(level, drone, from, orientation.clockwise()),
+        Action::COUNTERCLOCKWISE => step_turn(level, drone, from, orientation.counterclockwise())
+    }
+}
+
+fn explore<F>(level: &Level, drone: &Drone, rate: F) -> Option<VecDeque<Action>>
+    where F: Fn(&Level, &Drone, &Point, Orientation) -> f64
+{
+    explore_impl(level, drone, rate, &Scorer::default()).map(|(path, _, _)| path)
+}
+
+// Tiny pseudo-random share of a rating, to break ties between equally rated positions
+// differently for every seed. None for seed 0
+fn jitter(pos: &Point, seed: u64) -> f64 {
+    if seed == 0 { return 0.; }
+    let mut hasher = FnvHasher::default();
+    hasher.write_u64(seed);
+    pos.hash(&mut hasher);
+    (hasher.finish() % 1000) as f64 * 1e-9
+}
+
+fn explore_wrapping(level: &Level, drone: &Drone, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+    let rate = |level: &Level, drone: &Drone, pos: &Point, orientation| scorer.rate(level, drone, pos, orientation) * (1. + jitter(pos, seed));
+    explore_impl(level, drone, rate, scorer).map(|(path, _, _)| path)
+}
+
+// Rating of a position is divided by plan length to the power of scorer.decay.
+// Plans grow scorer.horizon steps longer at a time until something is found
+fn explore_impl<F>(level: &Level, drone: &Drone, rate: F, scorer: &Scorer) -> Option<(VecDeque<Action>, Point, f64)>
+    where F: Fn(&Level, &Drone, &Point, Orientation) -> f64
+{
+    // positions are searched with every orientation, so that turning on the way counts
+    let mut seen: FnvHashSet<(Point, Orientation)> = FnvHashSet::default();
+    seen.insert((drone.pos, drone.orientation));
+    let mut queue: VecDeque<Plan> = VecDeque::with_capacity(100);
+    let mut best: Option<(VecDeque<Action>, Point, f64)> = None;
+    let mut max_len = scorer.horizon;
+    queue.push_back(Plan{plan:    VecDeque::new(),
+                         pos:     drone.pos,
+                         orientation: drone.orientation,
+                         wheels:  drone.wheels,
+                         drill:   drone.drill,
+                         drilled: FnvHashSet::default() });
+    loop {
+        if let Some(Plan{plan, pos, orientation, wheels, drill, drilled}) = queue.pop_front() {
+            if plan.len() >= max_len {
+                if best.is_some() {
+                    break best
+                } else {
+                    max_len += scorer.horizon;
+                }
+            }
+
+            let score = if plan.is_empty() { 0. } else { rate(level, drone, &pos, orientation) / (plan.len() as f64).powf(scorer.decay) };
+
+            if best.is_some() {
+                if score > best.as_ref().unwrap().2 { best = Some((plan.clone(), pos, score)); }
+            } else {
+                if score > 0. { best = Some((plan.clone(), pos, score)); }
+            }
+
+            for action in &[Action::LEFT, Action::RIGHT, Action::UP, Action::DOWN, Action::JUMP0, Action::JUMP1, Action::JUMP2, Action::CLOCKWISE, Action::COUNTERCLOCKWISE] {
+                if let Some((pos2, orientation2, _, new_drilled)) = step(level, None, &pos, orientation, action, wheels > 0, drill > 0, &drilled) {
+                    if !seen.insert((pos2, orientation2)) { continue; }
+                    let mut plan2 = plan.clone();
+                    plan2.push_back(*action);
+                    let mut drilled2 = drilled.clone();
+                    for p in new_drilled { drilled2.insert(p); }
+                    queue.push_back(Plan{
+                        plan:    plan2,
+                        pos:     pos2,
+                        orientation: orientation2,
+                        wheels:  wheels.saturating_sub(1),
+                        drill:   drill.saturating_sub(1),
+                        drilled: drilled2
+                    });
+                }
+            }
+        } else { break best }
+    }
+}
+
+fn find_clone_score(level: &Level, _: &Drone, pos: &Point, _: Orientation) -> f64 {
+    if level.bonuses.get(pos) == Some(&Bonus::CLONE) { 1. } else { 0. }
+}
+
+fn explore_clone(level: &Level, drone: &Drone, drone_idx: usize) -> Option<VecDeque<Action>> {
+    if drone_idx == 0
+       && level.bonuses.values().any(|&b| b == Bonus::CLONE)
+       && get_or(&level.collected, &Bonus::CLONE, 0) == 0 {
+        explore(level, drone, find_clone_score)
+    } else {
+        None
+    }
+}
+
+fn find_spawn_score(level: &Level, _: &Drone, pos: &Point, _: Orientation) -> f64 {
+    if level.spawns.contains(pos) { 1.} else { 0. }
+}
+
+fn explore_spawn(level: &Level, drone: &Drone, drone_idx: usize) -> Option<VecDeque<Action>> {
+    if drone_idx == 0 && get_or(&level.collected, &Bonus::CLONE, 0) > 0 {
+        explore(level, drone, find_spawn_score)
+    } else {
+        None
+    }
+}
+
+fn print_state(level: &Level, drones: &[Drone]) {
+    println!("\x1B[2J");
+    print_level(level, drones, 0);
+    println!("Empty {:?} Collected {:?}", level.zones_empty, level.collected);
+    for (i, drone) in drones.iter().enumerate() {
+        let plan: Vec<_> = drone.plan.iter().map(|action| match action { Action::UP => "↑", Action::DOWN => "↓", Action::LEFT => "←", Action::RIGHT => "→", Action::JUMP0 => "T0", Action::JUMP1 => "T1", Action::JUMP2 => "T2", Action::CLOCKWISE => "↻", Action::COUNTERCLOCKWISE => "↺" }).collect();
+        println!("{}: zone {} wheels {} drill {} at ({},{}) plan {}", i, zone_char(drone.zone), drone.wheels, drone.drill, drone.pos.x, drone.pos.y, plan.join(""));
+    }
+    thread::sleep(time::Duration::from_millis(DELAY));
+}
+
+fn solve_impl(level: &mut Level, drones: &mut Vec<Drone>, options: Options) -> Solution {
+    let interactive = options.interactive;
+    let mut strategy = strategy::by_name(&options);
+    if interactive { println!("\x1B[?1049h"); }
+    drones[0].wrap_bot(level);
+    while level.empty > 0 {
+        if interactive { print_state(level, drones); }
+        for drone_idx in 0..drones.len() {
+            if level.empty == 0 { break; }
+
+            drones[drone_idx].collect(level);
+            drones[drone_idx].wear_off();
+            let decision = strategy.decide(level, drones, drone_idx);
+            if let Some(clone) = drones[drone_idx].execute(decision, level) {
+                drones.push(clone);
+            }
+        }
+    }
+
+    if interactive {
+        print_state(level, drones);
+        println!("\x1B[?1049l");
+    }
+
+    Solution { paths: drones.iter().map(|d| d.path.clone()).collect() }
+}
+
+// A parsed map with its starting drone, can be solved any number of times
+#[derive(Clone)]
+pub struct Problem {
+    level:  Level,
+    drones: Vec<Drone>
+}
+
+impl Problem {
+    pub fn width(&self) -> isize { self.level.width }
+    pub fn height(&self) -> isize { self.level.height }
+    // Cells to wrap
+    pub fn area(&self) -> usize { self.level.empty }
+}
+
+pub fn parse_level(file: &str) -> Result<Problem, ParseError> {
+    parse_level_with(file, "")
+}
+
+// Same as parse_level, with boosters bought in advance in .buy format ("CCB")
+pub fn parse_level_with(file: &str, bought: &str) -> Result<Problem, ParseError> {
+    let (level, drones) = parser::parse_level(file, &parser::parse_buy(bought)?)?;
+    Ok(Problem { level, drones })
+}
+
+pub fn solve(problem: &Problem, options: &Options) -> Solution {
+    let mut level = problem.level.clone();
+    let zones = options.scorer.zones_count(level.zones_empty.len());
+    if zones != level.zones_empty.len() || options.seed != 0 {
+        let (zones, zones_empty) = parser::zones(zones, parser::ZONES_SEED.wrapping_add(options.seed), &level.grid, level.width, level.height);
+        level.zones = zones;
+        level.zones_empty = zones_empty;
+    }
+    let mut drones = problem.drones.clone();
+    solve_impl(&mut level, &mut drones, *options)
+}
+
+// Solutions with seeds 0, 1, 2... and their validated steps, None when invalid
+pub struct Restarts {
+    pub best:  Option<(u64, Solution)>, // seed and solution with the least steps
+    pub steps: Vec<Option<usize>>
+}
+
+// Restarts with a new seed while there's time. One that likely won't finish
+// in time isn't started, but there's always at least one
+pub fn solve_restarts(problem: &Problem, options: &Options, limit: time::Duration) -> Restarts {
+    let start = time::Instant::now();
+    let mut restarts = Restarts { best: None, steps: Vec::new() };
+    for seed in 0.. {
+        let elapsed = start.elapsed();
+        if seed > 0 && elapsed + elapsed / seed as u32 > limit { break; }
+        let solution = solve(problem, &Options { seed, ..*options });
+        let steps = validate(problem, &solution).ok();
+        if let Some(steps) = steps {
+            if restarts.best.as_ref().is_none_or(|(_, best)| steps < best.score()) {
+                restarts.best = Some((seed, solution));
+            }
+        }
+        restarts.steps.push(steps);
+    }
+    restarts
+}
+
+// Replays solution, returns time steps taken
+pub fn validate(problem: &Problem, solution: &Solution) -> Result<usize, Invalid> {
+    validator::validate(problem.level.clone(), problem.drones.clone(), solution)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The table is_reaching used before it handled arbitrary offsets:
+    // hands with x == 1 and y == -1..18, indexed by y+1
+    fn hand_blockers_table() -> Vec<Vec<Point>> {
+        let mut res: Vec<Vec<Point>> = Vec::with_capacity(20);
+        res.push(vec![Point::new(1, -1)]);
+        res.push(vec![Point::new(1,  0)]);
+        res.push(vec![Point::new(1,  1)]);
+        for maxy in 2..19 {
+            let mut val = Vec::with_capacity(maxy);
+            for y in 1..(maxy/2+1) { val.push(Point::new(0, y as isize)) }
+            for y in maxy.div_ceil(2)..(maxy+1) { val.push(Point::new(1, y as isize)) }
+            res.push(val);
+        }
+        res
+    }
+
+    fn sorted(mut points: Vec<Point>) -> Vec<(isize, isize)> {
+        let mut res: Vec<(isize, isize)> = points.drain(..).map(|p| (p.x, p.y)).collect();
+        res.sort();
+        res
+    }
+
+    #[test]
+    fn hand_blockers_match_table() {
+        for (idx, expected) in hand_blockers_table().into_iter().enumerate() {
+            let hand = Point::new(1, idx as isize - 1);
+            assert_eq!(sorted(hand_blockers(&hand)), sorted(expected), "hand {:?}", hand);
+        }
+    }
+
+    #[test]
+    fn hand_blockers_rotate() {
+        for x in -6..7 {
+            for y in -6..7 {
+                let hand = Point::new(x, y);
+                for orientation in &[Orientation::DOWN, Orientation::LEFT, Orientation::UP] {
+                    let rotated: Vec<Point> = hand_blockers(&hand).iter().map(|p| orientation.rotate(p)).collect();
+                    assert_eq!(sorted(hand_blockers(&orientation.rotate(&hand))), sorted(rotated), "hand {:?} {:?}", hand, orientation);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reaching_beyond_table() {
+        let problem = parse_level("(0,0),(40,0),(40,2),(0,2)#(0,0)#(30,1),(31,1),(31,2),(30,2)#").unwrap();
+        let (level, from) = (&problem.level, Point::new(0, 0));
+        assert!(is_reaching(level, &from, &Point::new(20, 1)));
+        assert!(is_reaching(level, &from, &Point::new(REACH + 11, 0)));
+        assert!(!is_reaching(level, &from, &Point::new(REACH + 11, 1)));
+        // the long ones are kept for the next time they're looked up
+        let far = Point::new(REACH + 11, 1);
+        assert_eq!(FAR_HAND_BLOCKERS.with(|far_blockers| far_blockers.borrow().get(&far).cloned()), Some(hand_blockers(&far)));
+        assert!(FAR_HAND_BLOCKERS.with(|far_blockers| !far_blockers.borrow().contains_key(&Point::new(20, 1))));
+    }
+
+    #[test]
+    fn follow_past_drilled() {
+        // with wheels RIGHT stops short of the wall at x == 3, so DOWN is planned from (2,1)
+        let problem = parse_level("(0,0),(6,0),(6,3),(0,3)#(1,1)#(3,0),(4,0),(4,2),(3,2)#").unwrap();
+        let (mut level, mut drone) = (problem.level, problem.drones[0].clone());
+        drone.wheels = 10;
+        drone.plan = VecDeque::from(vec![Action::RIGHT, Action::DOWN]);
+        let mut planned = (level.clone(), drone.clone());
+        planned.1.follow(&mut planned.0);
+        assert_eq!(planned.1.pos, Point::new(2, 1));
+        assert!(planned.1.can_follow(&planned.0));
+
+        // once another drone drills through it, RIGHT goes on into the wall and DOWN can't be taken
+        level.drill_cell(3, 1);
+        drone.follow(&mut level);
+        assert_eq!(drone.pos, Point::new(3, 1));
+        assert!(!drone.can_follow(&level));
+        let mut greedy = strategy::GreedyZoneStrategy::new(&Options::default(), 0, false, Box::new(strategy::GreedyPlanner));
+        let decision = strategy::Strategy::decide(&mut greedy, &level, &[drone.clone()], 0);
+        assert!(!matches!(decision, Decision::FOLLOW));
+        drone.execute(decision, &mut level);
+        assert!(drone.plan.is_empty() || drone.can_follow(&level));
+    }
+
+    #[test]
+    fn hand_blockers_corners() {
+        assert_eq!(sorted(hand_blockers(&Point::new(0, 0))), vec![]);
+        assert_eq!(sorted(hand_blockers(&Point::new(1, 1))), vec![(1, 1)]);
+        assert_eq!(sorted(hand_blockers(&Point::new(0, 3))), vec![(0, 1), (0, 2), (0, 3)]);
+        assert_eq!(sorted(hand_blockers(&Point::new(2, 1))), vec![(1, 0), (1, 1), (2, 1)]);
+        assert_eq!(sorted(hand_blockers(&Point::new(-2, -2))), vec![(-2, -2), (-1, -1)]);
+    }
+}
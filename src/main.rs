@@ -1,607 +1,554 @@
-#![allow(dead_code, unused_imports, unused_variables)]
+#![allow(clippy::upper_case_acronyms)]
 
-mod parser;
-
-use std::{env, fs, io, thread, time};
-use std::cmp::{min, max};
+use std::{env, fs, process, thread};
 use std::fs::{File};
 use std::io::prelude::*;
 use std::collections::{VecDeque};
-use std::time::{Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::sync::{Mutex, Arc};
-use std::hash::{Hash, Hasher};
-use fnv::{FnvHashMap, FnvHashSet};
 use regex::Regex;
-use lazy_static::lazy_static;
-
-const DELAY: u64 = 50;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use icfpc2019::{Options, Attach, Problem, Solution, Scorer, STRATEGIES, puzzle, shop, contest, viewer, export};
+use icfpc2019::results::{Results, Record};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Point { x: isize, y: isize }
-
-impl Point {
-    fn new(x: isize, y: isize) -> Point { Point{ x, y } }
+fn sol_filename(filename: &str) -> String {
+    Regex::new(r"\.desc$").unwrap().replace(filename, ".sol").into_owned()
 }
 
-impl Hash for Point {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_i16(self.x as i16);
-        state.write_i16(self.y as i16);
-    }
+fn desc_filename(filename: &str) -> String {
+    Regex::new(r"\.cond$").unwrap().replace(filename, ".desc").into_owned()
 }
 
-#[derive(Debug)]
-struct Line { from: Point, to: Point }
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Cell { EMPTY, BLOCKED, WRAPPED }
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Action { UP, RIGHT, DOWN, LEFT, JUMP0, JUMP1, JUMP2 }
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Bonus { HAND, WHEELS, DRILL, TELEPORT, CLONE }
-
-fn get_or<K>(m: &FnvHashMap<K, usize>, k: &K, default: usize) -> usize
-    where K: std::hash::Hash + Eq + std::marker::Sized
-{
-    if let Some(v) = m.get(k) { *v } else { default }
+fn buy_filename(filename: &str) -> String {
+    Regex::new(r"\.desc$").unwrap().replace(filename, ".buy").into_owned()
 }
 
-fn update<K>(m: &mut FnvHashMap<K, usize>, k: K, delta: isize)
-    where K: std::hash::Hash + Eq + std::marker::Sized
-{
-    let old_v: usize = get_or(m, &k, 0);
-    let new_v = old_v as isize + delta;
-    if new_v > 0 { m.insert(k, new_v as usize); }
-    else { m.remove(&k); }
+// results.csv is shared by all problems in the same directory
+fn results_filename(filename: &str) -> String {
+    Regex::new(r"[^/]*\.desc$").unwrap().replace(filename, "results.csv").into_owned()
 }
 
-fn hand_blockers() -> Vec<Vec<Point>> {
-    let mut res: Vec<Vec<Point>> = Vec::with_capacity(20);
-    res.push(vec![Point::new(1, -1)]);
-    res.push(vec![Point::new(1,  0)]);
-    res.push(vec![Point::new(1,  1)]);
-    for maxy in 2..19 {
-        let mut val = Vec::with_capacity(maxy);
-        for y in 1..(maxy/2+1) { val.push(Point::new(0, y as isize)) }
-        for y in (maxy+1)/2..(maxy+1) { val.push(Point::new(1, y as isize)) }
-        res.push(val);
-    }
-    res
+fn problem_name(filename: &str) -> String {
+    Regex::new(r"[^/]*$").unwrap().find(filename).unwrap().as_str().to_string()
 }
 
-lazy_static! {
-    // for hands with x == 1 and y == -1..18
-    // indexed by y+1: y == -1 -> [0]; y == 0 -> [1], ...
-    static ref HAND_BLOCKERS: Vec<Vec<Point>> = hand_blockers();
+fn scorer_filename(filename: &str) -> String {
+    Regex::new(r"\.desc$").unwrap().replace(filename, ".scorer").into_owned()
 }
 
-type Zone = u8;
-const UNDECIDED_ZONE: Zone = !0;
-fn zone_char(zone: Zone) -> char {
-    if zone < UNDECIDED_ZONE { (65 + zone) as char }
-    else { '-' }
+// Scorer tuned for the problem, if there's a .scorer file next to it
+fn read_scorer(filename: &str) -> Result<Option<Scorer>, String> {
+    match fs::read_to_string(scorer_filename(filename)) {
+        Ok(config) => icfpc2019::parse_scorer(&config).map(Some).map_err(|e| format!("{}: {}", scorer_filename(filename), e)),
+        Err(_)     => Ok(None)
+    }
 }
 
-pub struct Drone {
-    pos:    Point,
-    hands:  Vec<Point>,
-    wheels: usize,
-    drill:  usize,
-    path:   String,
-    plan:   VecDeque<Action>,
-    zone:   Zone
+// Boosters bought for the problem, if there's a .buy file next to it
+fn read_buy(filename: &str) -> String {
+    fs::read_to_string(buy_filename(filename)).unwrap_or_default()
 }
 
-impl Drone {
-    fn new(pos: Point) -> Drone {
-        Drone { pos,
-                hands:  vec![Point::new(0,0), Point::new(1,-1), Point::new(1,0), Point::new(1,1)],
-                wheels: 0,
-                drill:  0,
-                path:   String::new(),
-                plan:   VecDeque::new(),
-                zone:   UNDECIDED_ZONE}
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Summary { TEXT, JSON, CSV }
 
-    fn wrap_bot(&self, level: &mut Level) {
-        let mut to_wrap: FnvHashSet<Point> = FnvHashSet::default();
-        would_wrap(level, self, &self.pos, &mut to_wrap);
-        for p in to_wrap {
-            level.wrap_cell(p.x, p.y);
-        }
-    }
+const CSV_HEADER: &str = "problem,score,previous,time_ms,drones,spawns,finishes,restarts,scores";
 
-    fn choose_zone(&mut self, taken: &[u8], level: &Level) -> bool {
-        if self.zone == UNDECIDED_ZONE || level.zones_empty[self.zone as usize] == 0 {
-            let not_empty:  Vec<u8> = (0..level.zones_empty.len() as u8).filter(|&z| level.zones_empty[z as usize] > 0).collect();
-            let not_taken:  Vec<u8> = not_empty.iter().cloned().filter(|&z| taken.iter().all(|&t| t != z)).collect();
-            let looking_in: Vec<u8> = if not_taken.len() > 0 { not_taken } else { not_empty };
-            let rate = |level: &Level, drone: &Drone, pos: &Point| {
-                if level.get_cell(pos.x, pos.y) == Cell::EMPTY && looking_in.contains(&level.get_zone(pos.x, pos.y)) { 1. }
-                else { 0. }
-            };
+fn json_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
 
-            if let Some((plan, pos, _)) = explore_impl(level, self, rate) {
-                self.zone = level.get_zone(pos.x, pos.y);
-                self.plan = plan;
-            } else {
-                panic!("No zone left to choose")
+// Score and time for a problem, with spawn and finish step of every drone,
+// the best score before this run and steps of every restart (None if invalid)
+fn summary(filename: &str, solution: &Solution, millis: u128, previous: Option<usize>, restarts: &[Option<usize>], format: Summary) -> String {
+    let timeline = solution.timeline();
+    let steps = |f: &dyn Fn(&icfpc2019::Timing) -> usize| -> Vec<String> {
+        timeline.iter().map(|t| t.as_ref().map_or(String::from("-"), |t| f(t).to_string())).collect()
+    };
+    let restart_steps = |none: &str| -> Vec<String> { restarts.iter().map(|s| s.map_or(String::from(none), |s| s.to_string())).collect() };
+    match format {
+        Summary::TEXT => {
+            let mut line = format!("{} \tscore {} \ttime {} ms", filename, solution.score(), millis);
+            if timeline.len() > 1 {
+                let drones: Vec<String> = steps(&|t| t.spawn).iter().zip(steps(&|t| t.finish)).map(|(s, f)| format!("{}-{}", s, f)).collect();
+                line += &format!(" \tdrones {}", drones.join(" "));
             }
-            true
-        } else {
-            false
-        }
-    }
-
-    fn collect(&self, level: &mut Level) {
-        if let Some(bonus) = level.bonuses.get(&self.pos) {
-            if let Some(collected) = level.collected.get_mut(bonus) {
-                *collected += 1;
-            } else {
-                level.collected.insert(*bonus, 1);
+            if restarts.len() > 1 {
+                let mut valid: Vec<usize> = restarts.iter().flatten().cloned().collect();
+                valid.sort_unstable();
+                line += &format!(" \trestarts {} \tmin/median/max {}/{}/{}", restarts.len(), valid[0], valid[valid.len() / 2], valid[valid.len() - 1]);
+                if valid.len() < restarts.len() { line += &format!(" \tinvalid {}", restarts.len() - valid.len()); }
+            }
+            match previous {
+                Some(p) if p <= solution.score() => line += &format!(" \tkept {}", p),
+                Some(p) => line += &format!(" \tbest was {}", p),
+                None    => {}
             }
-            level.bonuses.remove(&self.pos);
+            line
         }
+        Summary::JSON => {
+            let drones: Vec<String> = timeline.iter().map(|t| match t {
+                Some(t) => format!("{{\"spawn\":{},\"finish\":{}}}", t.spawn, t.finish),
+                None    => String::from("null")
+            }).collect();
+            format!("{{\"problem\":{},\"score\":{},\"previous\":{},\"time_ms\":{},\"drones\":[{}],\"restarts\":[{}]}}", json_string(filename), solution.score(),
+                    previous.map_or(String::from("null"), |p| p.to_string()), millis, drones.join(","), restart_steps("null").join(","))
+        }
+        Summary::CSV => format!("{},{},{},{},{},{},{},{},{}", filename, solution.score(), previous.map_or(String::new(), |p| p.to_string()), millis,
+                                timeline.len(), steps(&|t| t.spawn).join(";"), steps(&|t| t.finish).join(";"), restarts.len(), restart_steps("-").join(";"))
     }
+}
 
-    fn wear_off(&mut self) {
-        if self.wheels > 0 { self.wheels -= 1; }
-        if self.drill > 0 { self.drill -= 1; }
-    }
-
-    fn has_space(&self, level: &Level) -> bool {
-        (1..5).all(   |i| level.valid(self.pos.x, self.pos.y+i) && level.get_cell(self.pos.x, self.pos.y+i) != Cell::BLOCKED)
-        || (1..5).all(|i| level.valid(self.pos.x, self.pos.y-i) && level.get_cell(self.pos.x, self.pos.y-i) != Cell::BLOCKED)
-        || (1..5).all(|i| level.valid(self.pos.x+i, self.pos.y) && level.get_cell(self.pos.x+i, self.pos.y) != Cell::BLOCKED)
-        || (1..5).all(|i| level.valid(self.pos.x-i, self.pos.y) && level.get_cell(self.pos.x-i, self.pos.y) != Cell::BLOCKED)
-    }
-
-    fn activate_wheels(&mut self, level: &mut Level) -> bool {
-        if get_or(&level.collected, &Bonus::WHEELS, 0) > 0
-           && self.wheels == 0
-           && self.has_space(level) {
-            update(&mut level.collected, Bonus::WHEELS, -1);
-            self.wheels = 51;
-            self.path += "F";
-            true
-        } else { false }
-    }
-
-    fn activate_drill(&mut self, level: &mut Level) -> bool {
-        if get_or(&level.collected, &Bonus::DRILL, 0) > 0
-           && self.drill == 0 {
-            update(&mut level.collected, Bonus::DRILL, -1);
-            self.drill = 31;
-            self.path += "L";
-            true
-        } else { false }
-    }
+// Guards read-modify-write of results.csv between solving threads
+static RESULTS_LOCK: Mutex<()> = Mutex::new(());
 
-    fn activate_hand(&mut self, level: &mut Level) -> bool {
-        if get_or(&level.collected, &Bonus::HAND, 0) > 0 {
-            update(&mut level.collected, Bonus::HAND, -1);
-            let new_hand = Point::new(1, self.hands.last().unwrap().y + 1);
-            self.path += &format!("B({},{})", new_hand.x, new_hand.y);
-            self.hands.push(new_hand);
-            true
-        } else { false }
+fn load_results(path: &str) -> Result<Results, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Results::parse(&contents).map_err(|e| format!("{}: {}", path, e)),
+        Err(_)       => Ok(Results::default())
     }
+}
 
-    fn set_beakon(&mut self, level: &mut Level) -> bool {
-        if get_or(&level.collected, &Bonus::TELEPORT, 0) > 0
-           && level.beakons.iter().all(|b| (b.x - self.pos.x).abs() + (b.y - self.pos.y).abs() >= 50)
-        {
-            update(&mut level.collected, Bonus::TELEPORT, -1);
-            self.path += "R";
-            level.beakons.push(self.pos);
-            true
-        } else { false }
-    }
+// Score of the .sol already on disk, if it's still valid for the problem
+fn sol_score(problem: &Problem, filename_sol: &str) -> Option<usize> {
+    let solution = icfpc2019::parse_solution(fs::read_to_string(filename_sol).ok()?.trim_end()).ok()?;
+    icfpc2019::validate(problem, &solution).ok()
+}
 
-    fn reduplicate(&mut self, level: &mut Level) -> Option<Drone> {
-        if get_or(&level.collected, &Bonus::CLONE, 0) > 0 && level.spawns.contains(&self.pos) {
-            update(&mut level.collected, Bonus::CLONE, -1);
-            self.path += "C";
-            Some(Drone::new(self.pos))
-        } else { None }
+// Writes .sol and its record in results.csv, unless the .sol on disk is at least as good.
+// Returns the score of the one on disk
+fn keep_best(filename: &str, problem: &Problem, solution: &Solution, bought: &str, strategy: &str, options: String) -> Result<Option<usize>, String> {
+    let _lock = RESULTS_LOCK.lock().unwrap();
+    let path = results_filename(filename);
+    let mut results = load_results(&path)?;
+    let previous = sol_score(problem, &sol_filename(filename));
+    if previous.is_none_or(|p| solution.score() < p) {
+        File::create(sol_filename(filename)).unwrap().write_all(solution.to_string().as_bytes()).unwrap();
+        results.insert(Record {
+            problem:   problem_name(filename),
+            score:     solution.score(),
+            previous,
+            bought:    bought.to_string(),
+            strategy:  strategy.to_string(),
+            options,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+        });
+        File::create(&path).unwrap().write_all(results.to_string().as_bytes()).unwrap();
     }
+    Ok(previous)
+}
 
-    fn act(&mut self, action: &Action, level: &mut Level) {
-        let wheels = self.wheels > 0;
-        let drill = self.drill > 0;
-        if let Some((pos, new_wrapped, new_drilled)) = step(level, self, &self.pos, action, wheels, drill, &FnvHashSet::default()) {
-            self.pos = pos;
-            match action {
-                Action::UP    => self.path += "W",
-                Action::DOWN  => self.path += "S",
-                Action::LEFT  => self.path += "A",
-                Action::RIGHT => self.path += "D",
-                Action::JUMP0 => self.path += &format!("T({},{})", level.beakons[0].x, level.beakons[0].y),
-                Action::JUMP1 => self.path += &format!("T({},{})", level.beakons[1].x, level.beakons[1].y),
-                Action::JUMP2 => self.path += &format!("T({},{})", level.beakons[2].x, level.beakons[2].y)
-            };
-            for p in new_wrapped {
-                level.wrap_cell(p.x, p.y);
-            }
-            for p in new_drilled {
-                level.drill_cell(p.x, p.y);
+// With a time limit, restarts with new seeds and keeps the best solution
+fn solve(filename: &str, options: Options, time_limit: Option<Duration>, format: Summary) {
+    if let Ok(contents) = fs::read_to_string(filename) {
+        let t_start = Instant::now();
+        let options = match read_scorer(filename) {
+            Ok(scorer) => Options { scorer: scorer.unwrap_or(options.scorer), ..options },
+            Err(error) => { println!("{} \tunparsable \t{}", filename, error); return; }
+        };
+        let bought = read_buy(filename);
+        match icfpc2019::parse_level_with(&contents, &bought) {
+            Ok(problem) => {
+                let restarts = match time_limit {
+                    Some(limit) => icfpc2019::solve_restarts(&problem, &options, limit),
+                    None => {
+                        let solution = icfpc2019::solve(&problem, &options);
+                        match icfpc2019::validate(&problem, &solution) {
+                            Ok(steps)    => icfpc2019::Restarts { best: Some((0, solution)), steps: vec![Some(steps)] },
+                            Err(invalid) => { println!("{} \tinvalid \t{}", filename, invalid); return; }
+                        }
+                    }
+                };
+                let millis = t_start.elapsed().as_millis();
+                let (seed, solution) = match restarts.best {
+                    Some(best) => best,
+                    None       => { println!("{} \tinvalid \tall {} restarts", filename, restarts.steps.len()); return; }
+                };
+                match keep_best(filename, &problem, &solution, bought.trim(), options.strategy, Options { seed, ..options }.to_string()) {
+                    Ok(previous) => println!("{}", summary(filename, &solution, millis, previous, &restarts.steps, format)),
+                    Err(error)   => println!("{} \tunreadable results \t{}", filename, error)
+                }
             }
-        } else {
-            panic!("Unwalkable from ({},{}) {:?}", self.pos.x, self.pos.y, action);
+            Err(error) => println!("{} \tunparsable \t{}", filename, error)
         }
+    } else {
+        println!("Failed to read {}", filename);
     }
 }
 
-pub struct Level {
-    grid:        Vec<Cell>,
-    weights:     Vec<u8>,
-    zones:       Vec<Zone>,
-    width:       isize,
-    height:      isize,
-    empty:       usize,
-    zones_empty: Vec<usize>,
-    spawns:      FnvHashSet<Point>,
-    beakons:     Vec<Point>,
-    bonuses:     FnvHashMap<Point, Bonus>,
-    collected:   FnvHashMap<Bonus, usize>
+// Solves with the given options and purchase from .buy, returns validated steps
+fn solve_tuned(filename: &str, options: Options) -> Option<(usize, Solution)> {
+    let contents = fs::read_to_string(filename).ok()?;
+    let problem = icfpc2019::parse_level_with(&contents, &read_buy(filename)).ok()?;
+    let solution = icfpc2019::solve(&problem, &options);
+    icfpc2019::validate(&problem, &solution).ok().map(|steps| (steps, solution))
 }
 
-impl Level {
-    fn grid_idx(&self, x: isize, y: isize) -> usize {
-        (x + y * self.width) as usize
-    }
-
-    fn get_cell(&self, x: isize, y: isize) -> Cell {
-        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
-        self.grid[self.grid_idx(x, y)]
-    }
-
-    fn get_zone(&self, x: isize, y: isize) -> u8 {
-        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
-        self.zones[self.grid_idx(x, y)]
-    }
-
-    fn wrap_cell(&mut self, x: isize, y: isize) {
-        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
-        debug_assert!(self.get_cell(x, y) == Cell::EMPTY);
-        let idx = self.grid_idx(x, y);
-        self.empty -= 1;
-        let zone = self.zones[idx];
-        if zone < 255 {
-            self.zones_empty[zone as usize] -= 1;
+// Evolutionary search over scorer knobs, per problem. Every generation tries mutations
+// of the best scorer so far, starting from .scorer files or --scorer. Writes the best
+// ones found to .scorer files and keeps their solutions if they're better than .sol
+fn tune(mut filenames: Vec<String>, sample: Option<usize>, generations: usize, population: usize, threads: usize, options: Options) {
+    let mut rng = rand_pcg::Pcg32::seed_from_u64(2019);
+    if let Some(sample) = sample {
+        filenames.shuffle(&mut rng);
+        filenames.truncate(sample);
+        filenames.sort();
+    }
+    // best scorer per problem with its steps and solution
+    let mut best: Vec<(Scorer, Option<(usize, Solution)>)> = Vec::with_capacity(filenames.len());
+    for filename in &filenames {
+        match read_scorer(filename) {
+            Ok(scorer) => best.push((scorer.unwrap_or(options.scorer), None)),
+            Err(error) => { println!("{} \tunparsable \t{}", filename, error); return; }
         }
-        self.grid[idx] = Cell::WRAPPED;
     }
+    // steps with the scorer tuning starts from
+    let mut initial: Vec<Option<usize>> = Vec::new();
 
-    fn drill_cell(&mut self, x: isize, y: isize) {
-        debug_assert!(x >= 0 && x < self.width && y >= 0 && y < self.height);
-        debug_assert!(self.get_cell(x, y) == Cell::BLOCKED);
-        let idx = self.grid_idx(x, y);
-        self.grid[idx] = Cell::WRAPPED;
-    }
-
-    fn valid(&self, x: isize, y: isize) -> bool {
-        x >= 0 && x < self.width as isize && y >= 0 && y < self.height as isize
-    }
-
-    fn walkable(&self, x: isize, y: isize) -> bool {
-        self.valid(x, y) && self.get_cell(x, y) != Cell::BLOCKED
-    }
-}
-
-fn print_level(level: &Level, drones: &[Drone]) {
-    let ymin = max(0, min(drones[0].pos.y - 25, level.height - 50));
-    let ymax = min(max(drones[0].pos.y + 25, 50), level.height);
-    let xmin = max(0, min(drones[0].pos.x - 50, level.width - 100));
-    let xmax = min(max(drones[0].pos.x + 50, 100), level.width);
-
-    for y in (ymin..ymax).rev() {
-        for x in xmin..xmax {
-            let point = Point::new(x, y);
-
-            let bg = if drones.iter().find(|d| d.hands.iter().find(|h| {
-                       d.pos.x + h.x == x as isize && d.pos.y + h.y == y as isize && is_reaching(level, &d.pos, &h)
-                     }).is_some()).is_some() { "\x1B[48;5;202m" }
-            else if level.bonuses.get(&point).is_some() { "\x1B[48;5;33m\x1B[38;5;15m" }
-            else if level.spawns.contains(&point)       { "\x1B[48;5;33m\x1B[38;5;15m" }
-            else if level.beakons.contains(&point)      { "\x1B[48;5;33m\x1B[38;5;15m" }
-            else {
-                match level.get_cell(x, y) {
-                    Cell::EMPTY   => { "\x1B[48;5;252m" }
-                    Cell::BLOCKED => { "\x1B[48;5;240m" }
-                    Cell::WRAPPED => { "\x1B[48;5;227m" }
+    for generation in 0..=generations {
+        let tries = if generation == 0 { 1 } else { population };
+        let mut tasks: VecDeque<(String, Scorer)> = VecDeque::new();
+        for (filename, (scorer, _)) in filenames.iter().zip(&best) {
+            for _ in 0..tries {
+                tasks.push_back((filename.clone(), if generation == 0 { *scorer } else { scorer.mutate(&mut rng) }));
+            }
+        }
+        let results = doall(tasks, threads, move |(f, scorer)| (scorer, solve_tuned(&f, Options { scorer, ..options })));
+        for (i, (scorer, res)) in results.into_iter().enumerate() {
+            let entry = &mut best[i / tries];
+            if let Some((steps, solution)) = res {
+                if entry.1.as_ref().is_none_or(|(b, _)| steps < *b) {
+                    *entry = (scorer, Some((steps, solution)));
                 }
-            };
-
-            let char = if let Some((idx, _)) = drones.iter().enumerate().find(|(idx, d)| d.hands.iter().find(|h| {
-                       d.pos.x + h.x == x as isize && d.pos.y + h.y == y as isize && is_reaching(level, &d.pos, &h)
-                     }).is_some()) { idx.to_string() }
-            else if let Some(bonus) = level.bonuses.get(&point) {
-                    String::from(match bonus {
-                        Bonus::HAND     => { "B" }
-                        Bonus::WHEELS   => { "F" }
-                        Bonus::DRILL    => { "L" }
-                        Bonus::TELEPORT => { "R" }
-                        Bonus::CLONE    => { "C" }
-                    })
-                } else if level.spawns.contains(&point) {
-                    String::from("X")
-                } else if let Some(beakon_idx) = level.beakons.iter().position(|&x| x == point) {
-                    beakon_idx.to_string()
-                } else {
-                    zone_char(level.get_zone(x, y)).to_string()
-                };
-            print!("{}{}\x1B[0m", bg, char);
+            }
         }
-        println!()
-    }
-    println!()
-}
-
-struct Plan {
-    plan:    VecDeque<Action>,
-    pos:     Point,
-    wheels:  usize,
-    drill:   usize,
-    drilled: FnvHashSet<Point>
-}
-
-fn max_wrapping(level: &Level, drone: &Drone, pos: &Point) -> f64 {
-    if level.get_zone(pos.x, pos.y) != drone.zone { 0. }
-    else if level.bonuses.contains_key(pos) { 100. }
-    else {
-        let mut wrapped: FnvHashSet<Point> = FnvHashSet::default();
-        would_wrap(level, drone, pos, &mut wrapped);
-        wrapped.iter().map(|p| 1.0_f64.max(level.weights[level.grid_idx(p.x, p.y)] as f64)).sum()
+        if generation == 0 {
+            initial = best.iter().map(|(_, b)| b.as_ref().map(|(steps, _)| *steps)).collect();
+        }
+        let total: usize = best.iter().filter_map(|(_, b)| b.as_ref().map(|(steps, _)| steps)).sum();
+        println!("Generation {} \ttotal {}", generation, total);
     }
-}
-
-fn is_reaching(level: &Level, from: &Point, hand: &Point) -> bool {
-    hand.x == 0 || HAND_BLOCKERS[(hand.y+1) as usize].iter().all(|p| level.walkable(from.x+p.x, from.y+p.y))
-}
 
-fn would_wrap(level: &Level, drone: &Drone, pos: &Point, wrapped: &mut FnvHashSet<Point>) {
-    for hand in &drone.hands {
-        if is_reaching(level, pos, &hand) {
-            let hand_pos = Point::new(pos.x + hand.x, pos.y + hand.y);
-            if level.get_cell(hand_pos.x, hand_pos.y) == Cell::EMPTY {
-                wrapped.insert(hand_pos);
+    for ((filename, (scorer, b)), initial) in filenames.iter().zip(&best).zip(&initial) {
+        match (b, initial) {
+            (Some((steps, solution)), Some(initial)) => {
+                let tuned = Options { scorer: *scorer, ..options };
+                if steps < initial {
+                    File::create(scorer_filename(filename)).unwrap().write_all(format!("{}\n", scorer).as_bytes()).unwrap();
+                }
+                let problem = icfpc2019::parse_level_with(&fs::read_to_string(filename).unwrap(), &read_buy(filename)).unwrap();
+                let kept = match keep_best(filename, &problem, solution, read_buy(filename).trim(), tuned.strategy, tuned.to_string()) {
+                    Ok(Some(previous)) if previous <= *steps => format!(" \tkept {}", previous),
+                    Ok(_)      => String::new(),
+                    Err(error) => format!(" \tunreadable results \t{}", error)
+                };
+                println!("{} \tscore {} -> {} \t{}{}", filename, initial, steps, scorer, kept);
             }
+            _ => println!("{} \tfailed", filename)
         }
     }
 }
 
-fn step_move(level: &Level, drone: &Drone, from: &Point, dx: isize, dy: isize, wheels: bool, drill: bool, drilled: &FnvHashSet<Point>) -> Option<(Point, FnvHashSet<Point>, FnvHashSet<Point>)>
-{
-    let mut to = Point::new(from.x + dx, from.y + dy);
-    let mut new_wrapped = FnvHashSet::default();
-    let mut new_drilled = FnvHashSet::default();
-    if drilled.contains(&to) || (drill && level.valid(to.x, to.y)) || level.walkable(to.x, to.y) {
-        would_wrap(level, drone, &to, &mut new_wrapped);
-        if drill && !drilled.contains(&to) && !level.walkable(to.x, to.y) {
-            new_drilled.insert(to);
+// Best solutions recorded for the problems, biggest improvements first
+fn results(filenames: Vec<String>) {
+    let mut loaded: Vec<(String, Results)> = Vec::new();
+    let mut rows: Vec<(String, Option<Record>)> = Vec::new();
+    for filename in filenames {
+        let path = results_filename(&filename);
+        if !loaded.iter().any(|(p, _)| *p == path) {
+            match load_results(&path) {
+                Ok(results) => loaded.push((path.clone(), results)),
+                Err(error)  => { println!("{}", error); return; }
+            }
         }
-        if wheels {
-            let to2 = Point::new(to.x + dx, to.y + dy);
-            if drilled.contains(&to2) || (drill && level.valid(to2.x, to2.y)) || level.walkable(to2.x, to2.y) {
-                would_wrap(level, drone, &to2, &mut new_wrapped);
-                if drill && !drilled.contains(&to2) && level.valid(to2.x, to2.y) && !level.walkable(to2.x, to2.y) {
-                    new_drilled.insert(to2);
-                }
-                to = to2;
+        let results = &loaded.iter().find(|(p, _)| *p == path).unwrap().1;
+        rows.push((filename.clone(), results.records.get(&problem_name(&filename)).cloned()));
+    }
+    rows.sort_by_key(|(filename, record)| (record.is_none(), record.as_ref().and_then(|r| r.delta()).unwrap_or(0), filename.clone()));
+
+    println!("{:<28} {:>8} {:>8} {:>7}  {:<5} {:<14} {:<20} when", "problem", "best", "previous", "delta", "buy", "strategy", "options");
+    let (mut total, mut gained) = (0, 0);
+    for (filename, record) in &rows {
+        match record {
+            Some(r) => {
+                total += r.score;
+                gained -= r.delta().unwrap_or(0);
+                println!("{:<28} {:>8} {:>8} {:>7}  {:<5} {:<14} {:<20} {}", filename, r.score,
+                         r.previous.map_or(String::from("-"), |p| p.to_string()), r.delta().map_or(String::from("-"), |d| format!("{:+}", d)),
+                         r.bought, r.strategy, r.options, icfpc2019::results::date(r.timestamp));
             }
+            None => println!("{:<28} {:>8}", filename, "-")
         }
-        Some((to, new_wrapped, new_drilled))
-    } else {
-        None
     }
+    println!("Total {} steps over {} of {} problems, {} gained on last improvements", total, rows.iter().filter(|(_, r)| r.is_some()).count(), rows.len(), gained);
 }
 
-fn step_jump(level: &Level, drone: &Drone, beakon_idx: usize) -> Option<(Point, FnvHashSet<Point>, FnvHashSet<Point>)>
-{
-    if beakon_idx < level.beakons.len() {
-        let to = level.beakons[beakon_idx];
-        let mut new_wrapped = FnvHashSet::default();
-        would_wrap(level, drone, &to, &mut new_wrapped);
-        Some((to, new_wrapped, FnvHashSet::default()))
-    } else {
-        None
+fn validate(filename: &str, filename_sol: &str) {
+    match (fs::read_to_string(filename), fs::read_to_string(filename_sol)) {
+        (Ok(contents), Ok(solution)) => {
+            match (icfpc2019::parse_level_with(&contents, &read_buy(filename)), icfpc2019::parse_solution(solution.trim_end())) {
+                (Ok(problem), Ok(solution)) => match icfpc2019::validate(&problem, &solution) {
+                    Ok(steps)    => println!("{} \tvalid \tscore {}", filename_sol, steps),
+                    Err(invalid) => println!("{} \tinvalid \t{}", filename_sol, invalid)
+                },
+                (Err(error), _) => println!("{} \tunparsable \t{}", filename, error),
+                (_, Err(error)) => println!("{} \tunparsable \t{}", filename_sol, error)
+            }
+        }
+        (Err(_), _) => println!("Failed to read {}", filename),
+        (_, Err(_)) => println!("Failed to read {}", filename_sol)
     }
 }
 
-fn step(level: &Level, drone: &Drone, from: &Point, action: &Action, wheels: bool, drill: bool, drilled: &FnvHashSet<Point>) -> Option<(Point, FnvHashSet<Point>, FnvHashSet<Point>)> {
-    match action {
-        Action::LEFT  => step_move(level, drone, from, -1,  0, wheels, drill, drilled),
-        Action::RIGHT => step_move(level, drone, from,  1,  0, wheels, drill, drilled),
-        Action::UP    => step_move(level, drone, from,  0,  1, wheels, drill, drilled),
-        Action::DOWN  => step_move(level, drone, from,  0, -1, wheels, drill, drilled),
-        Action::JUMP0 => step_jump(level, drone, 0),
-        Action::JUMP1 => step_jump(level, drone, 1),
-        Action::JUMP2 => step_jump(level, drone, 2)
+// Shortens a solution and keeps it if it beats the .sol of the problem. The record
+// gets strategy "optimize" with the strategy and options it was optimized from
+fn optimize(filename: &str, filename_sol: &str) {
+    match (fs::read_to_string(filename), fs::read_to_string(filename_sol)) {
+        (Ok(contents), Ok(solution)) => {
+            let t_start = Instant::now();
+            let bought = read_buy(filename);
+            match (icfpc2019::parse_level_with(&contents, &bought), icfpc2019::parse_solution(solution.trim_end())) {
+                (Ok(problem), Ok(solution)) => match icfpc2019::optimize(&problem, &solution) {
+                    Ok(Some(optimized)) => {
+                        let from = load_results(&results_filename(filename)).ok()
+                            .and_then(|results| results.records.get(&problem_name(filename)).map(|r| format!("{} {}", r.strategy, r.options)))
+                            .filter(|_| filename_sol == sol_filename(filename))
+                            .unwrap_or_default();
+                        let line = format!("{} \tscore {} -> {} \ttime {} ms", filename_sol, solution.score(), optimized.score(), t_start.elapsed().as_millis());
+                        match keep_best(filename, &problem, &optimized, bought.trim(), "optimize", from) {
+                            Ok(Some(previous)) if previous <= optimized.score() => println!("{} \tkept {}", line, previous),
+                            Ok(_)      => println!("{}", line),
+                            Err(error) => println!("{} \tunreadable results \t{}", filename, error)
+                        }
+                    }
+                    Ok(None)     => println!("{} \tscore {} \tno shorter solution found \ttime {} ms", filename_sol, solution.score(), t_start.elapsed().as_millis()),
+                    Err(invalid) => println!("{} \tinvalid \t{}", filename_sol, invalid)
+                },
+                (Err(error), _) => println!("{} \tunparsable \t{}", filename, error),
+                (_, Err(error)) => println!("{} \tunparsable \t{}", filename_sol, error)
+            }
+        }
+        (Err(_), _) => println!("Failed to read {}", filename),
+        (_, Err(_)) => println!("Failed to read {}", filename_sol)
     }
 }
 
-fn explore<F>(level: &Level, drone: &Drone, rate: F) -> Option<VecDeque<Action>>
-    where F: Fn(&Level, &Drone, &Point) -> f64
-{
-    explore_impl(level, drone, rate).and_then(|(path, _, _)| Some(path))
-}
-
-fn explore_impl<F>(level: &Level, drone: &Drone, rate: F) -> Option<(VecDeque<Action>, Point, f64)>
-    where F: Fn(&Level, &Drone, &Point) -> f64
-{
-    let mut seen: FnvHashSet<Point> = FnvHashSet::default();
-    let mut queue: VecDeque<Plan> = VecDeque::with_capacity(100);
-    let mut best: Option<(VecDeque<Action>, Point, f64)> = None;
-    let mut max_len = 5;
-    queue.push_back(Plan{plan:    VecDeque::new(),
-                         pos:     drone.pos,
-                         wheels:  drone.wheels,
-                         drill:   drone.drill,
-                         drilled: FnvHashSet::default() });
-    loop {
-        if let Some(Plan{plan, pos, wheels, drill, drilled}) = queue.pop_front() {
-            if plan.len() >= max_len {
-                if best.is_some() {
-                    break best
-                } else {
-                    max_len += 5;
+fn replay(filename: &str, filename_sol: &str) {
+    match (fs::read_to_string(filename), fs::read_to_string(filename_sol)) {
+        (Ok(contents), Ok(solution)) => {
+            match (icfpc2019::parse_level_with(&contents, &read_buy(filename)), icfpc2019::parse_solution(solution.trim_end())) {
+                (Ok(problem), Ok(solution)) => {
+                    if let Err(reason) = viewer::replay(&problem, &solution) {
+                        println!("{} \tcannot replay \t{}", filename_sol, reason);
+                    }
                 }
+                (Err(error), _) => println!("{} \tunparsable \t{}", filename, error),
+                (_, Err(error)) => println!("{} \tunparsable \t{}", filename_sol, error)
             }
+        }
+        (Err(_), _) => println!("Failed to read {}", filename),
+        (_, Err(_)) => println!("Failed to read {}", filename_sol)
+    }
+}
 
-            let score = if plan.is_empty() { 0. } else { rate(level, drone, &pos) / plan.len() as f64 };
-
-            if best.is_some() {
-                if score > best.as_ref().unwrap().2 { best = Some((plan.clone(), pos, score)); }
-            } else {
-                if score > 0. { best = Some((plan.clone(), pos, score)); }
-            }
-
-            for action in &[Action::LEFT, Action::RIGHT, Action::UP, Action::DOWN, Action::JUMP0, Action::JUMP1, Action::JUMP2] {
-                if let Some((pos2, new_wrapped, new_drilled)) = step(level, drone, &pos, action, wheels > 0, drill > 0, &drilled) {
-                    if seen.contains(&pos2) { continue; }
-                    seen.insert(pos2);
-                    let mut plan2 = plan.clone();
-                    plan2.push_back(*action);
-                    let mut drilled2 = drilled.clone();
-                    for p in new_drilled { drilled2.insert(p); }
-                    queue.push_back(Plan{
-                        plan:    plan2,
-                        pos:     pos2,
-                        wheels:  if wheels > 1 { wheels - 1 } else { 0 },
-                        drill:   if drill > 1  { drill - 1 }  else { 0 },
-                        drilled: drilled2
-                    });
+// Writes prob-NNN.svg (or .html with a time slider) next to prob-NNN.desc
+fn export(filename: &str, filename_sol: &str, html: bool) {
+    match (fs::read_to_string(filename), fs::read_to_string(filename_sol)) {
+        (Ok(contents), Ok(solution)) => {
+            match (icfpc2019::parse_level_with(&contents, &read_buy(filename)), icfpc2019::parse_solution(solution.trim_end())) {
+                (Ok(problem), Ok(solution)) => {
+                    let (filename_out, out) = if html {
+                        (Regex::new(r"\.desc$").unwrap().replace(filename, ".html").into_owned(), export::html(&problem, &solution))
+                    } else {
+                        (Regex::new(r"\.desc$").unwrap().replace(filename, ".svg").into_owned(), export::svg(&problem, &solution))
+                    };
+                    File::create(&filename_out).unwrap().write_all(out.as_bytes()).unwrap();
+                    println!("{} \texported \t{} KB", filename_out, out.len() / 1024);
                 }
+                (Err(error), _) => println!("{} \tunparsable \t{}", filename, error),
+                (_, Err(error)) => println!("{} \tunparsable \t{}", filename_sol, error)
             }
-        } else { break best }
+        }
+        (Err(_), _) => println!("Failed to read {}", filename),
+        (_, Err(_)) => println!("Failed to read {}", filename_sol)
     }
 }
 
-fn find_clone_score(level: &Level, drone: &Drone, pos: &Point) -> f64 {
-    if level.bonuses.get(pos) == Some(&Bonus::CLONE) { 1. } else { 0. }
+// Solves with a given purchase, ignoring .buy files. Returns map weight and validated score
+fn solve_bought(filename: &str, bought: &str, options: Options) -> Option<(f64, usize)> {
+    let contents = fs::read_to_string(filename).ok()?;
+    let problem = icfpc2019::parse_level_with(&contents, bought).ok()?;
+    let solution = icfpc2019::solve(&problem, &options);
+    let steps = icfpc2019::validate(&problem, &solution).ok()?;
+    Some((shop::weight(problem.width(), problem.height()), steps))
 }
 
-fn explore_clone(level: &Level, drone: &Drone, drone_idx: usize) -> Option<VecDeque<Action>> {
-    if drone_idx == 0
-       && level.bonuses.values().any(|&b| b == Bonus::CLONE)
-       && get_or(&level.collected, &Bonus::CLONE, 0) == 0 {
-        explore(level, drone, find_clone_score)
-    } else {
-        None
+// Tries every candidate purchase on every problem, then spends coins where
+// they raise the estimated total score most. Writes .buy files and a report
+fn buy(filenames: Vec<String>, coins: usize, threads: usize, options: Options, report: &str) {
+    let mut tasks: VecDeque<(String, &'static str)> = VecDeque::new();
+    for filename in &filenames {
+        for candidate in shop::CANDIDATES.iter() {
+            tasks.push_back((filename.clone(), candidate));
+        }
     }
-}
-
-fn find_spawn_score(level: &Level, drone: &Drone, pos: &Point) -> f64 {
-    if level.spawns.contains(pos) { 1.} else { 0. }
-}
-
-fn explore_spawn(level: &Level, drone: &Drone, drone_idx: usize) -> Option<VecDeque<Action>> {
-    if drone_idx == 0 && get_or(&level.collected, &Bonus::CLONE, 0) > 0 {
-        explore(level, drone, find_spawn_score)
-    } else {
-        None
+    let results = doall(tasks, threads, move |(f, candidate)| {
+        let res = solve_bought(&f, candidate, options);
+        match res {
+            Some((_, steps)) => println!("{} \tbuy {:?} \tscore {}", f, candidate, steps),
+            None             => println!("{} \tbuy {:?} \tfailed", f, candidate)
+        }
+        res
+    });
+
+    // options per problem: (candidate, cost, steps, estimated score); no purchase always first
+    let mut problems: Vec<Vec<(&str, usize, usize, f64)>> = Vec::with_capacity(filenames.len());
+    for chunk in results.chunks(shop::CANDIDATES.len()) {
+        let mut options = Vec::new();
+        if let Some((weight, base_steps)) = chunk[0] {
+            for (candidate, res) in shop::CANDIDATES.iter().zip(chunk) {
+                if let Some((_, steps)) = res {
+                    options.push((*candidate, shop::cost(candidate), *steps, shop::estimate(weight, base_steps, *steps)));
+                }
+            }
+        } else {
+            options.push(("", 0, 0, 0.0));
+        }
+        problems.push(options);
+    }
+
+    let knapsack: Vec<Vec<(usize, f64)>> = problems.iter().map(|o| o.iter().map(|&(_, cost, _, score)| (cost, score)).collect()).collect();
+    let chosen = shop::allocate(&knapsack, coins);
+
+    let mut lines = Vec::with_capacity(filenames.len() + 1);
+    let (mut spent, mut base_total, mut total) = (0, 0.0, 0.0);
+    for ((filename, options), &idx) in filenames.iter().zip(&problems).zip(&chosen) {
+        let (_, _, base_steps, base_score) = options[0];
+        let (candidate, cost, steps, score) = options[idx];
+        spent += cost;
+        base_total += base_score;
+        total += score;
+        if candidate.is_empty() {
+            let _ = fs::remove_file(buy_filename(filename));
+        } else {
+            File::create(buy_filename(filename)).unwrap().write_all(candidate.as_bytes()).unwrap();
+        }
+        lines.push(format!("{} \tbuy {:?} \tcost {} \tsteps {} -> {} \testimate {:.0} -> {:.0}", filename, candidate, cost, base_steps, steps, base_score, score));
     }
+    lines.push(format!("Spent {} of {} coins \testimate {:.0} -> {:.0} (+{:.0})", spent, coins, base_total, total, total - base_total));
+    let text = lines.join("\n") + "\n";
+    print!("{}", text);
+    File::create(report).unwrap().write_all(text.as_bytes()).unwrap();
 }
 
-fn print_state(level: &Level, drones: &[Drone]) {
-    println!("\x1B[2J");
-    print_level(level, &drones);
-    println!("Empty {:?} Collected {:?}", level.zones_empty, level.collected);
-    for (i, drone) in drones.iter().enumerate() {
-        let plan: Vec<_> = drone.plan.iter().map(|action| match action { Action::UP => "↑", Action::DOWN => "↓", Action::LEFT => "←", Action::RIGHT => "→", Action::JUMP0 => "T0", Action::JUMP1 => "T1", Action::JUMP2 => "T2", }).collect();
-        println!("{}: zone {} wheels {} drill {} at ({},{}) plan {}", i, zone_char(drone.zone), drone.wheels, drone.drill, drone.pos.x, drone.pos.y, plan.join(""));
-    }
-    thread::sleep(time::Duration::from_millis(DELAY));
+// Map size and contest weight, with our validated steps if there's a valid .sol
+fn sol_steps(filename: &str) -> Option<(String, f64, Option<usize>)> {
+    let contents = fs::read_to_string(filename).ok()?;
+    let problem = icfpc2019::parse_level_with(&contents, &read_buy(filename)).ok()?;
+    let steps = fs::read_to_string(sol_filename(filename)).ok()
+        .and_then(|sol| icfpc2019::parse_solution(sol.trim_end()).ok())
+        .and_then(|solution| icfpc2019::validate(&problem, &solution).ok());
+    Some((format!("{}x{}", problem.width(), problem.height()), shop::weight(problem.width(), problem.height()), steps))
 }
 
-fn solve_impl(level: &mut Level, drones: &mut Vec<Drone>, interactive: bool) -> String {
-    if interactive { println!("\x1B[?1049h"); }
-    drones[0].wrap_bot(level);
-    while level.empty > 0 {
-        if interactive { print_state(level, drones); }
-        for drone_idx in 0..drones.len() {
-            if level.empty <= 0 { break; }
-
-            let taken: Vec<_> = drones.iter().map(|d| d.zone).collect();
-            let mut drone = &mut drones[drone_idx];
-            drone.collect(level);
-            drone.wear_off();
-            drone.choose_zone(&taken, level);
-
-            if drone.plan.is_empty() {
-                if let Some(clone) = drone.reduplicate(level) {
-                    drones.push(clone);
-                    continue;
-                }
-
-                if drone.activate_wheels(level)
-                   || drone.activate_drill(level)
-                   || drone.activate_hand(level)
-                   || drone.set_beakon(level)
-                { continue; }
-
-                if let Some(plan) = explore_clone(level, drone, drone_idx)
-                                    .or_else(|| explore_spawn(level, drone, drone_idx))
-                                    .or_else(|| explore(level, drone, max_wrapping)) {
-                    drone.plan = plan;
-                }
-            }
-
-            if let Some(action) = drone.plan.pop_front() {
-                drone.act(&action, level);
-            } else if drone.wheels > 0 {
-                drone.path += "Z";
-            } else {
-                panic!("Nothing to do");
+// Expected contest points per problem against best known times, biggest possible gain first.
+// Problems without a known best time count ours as the best
+fn estimate(filenames: Vec<String>, threads: usize, best: &str) {
+    let best = match fs::read_to_string(best).map_err(|e| e.to_string()).and_then(|s| contest::parse_best(&s)) {
+        Ok(best)   => best,
+        Err(error) => { println!("{} \tunreadable \t{}", best, error); return; }
+    };
+    let solved = doall(filenames.iter().cloned().collect(), threads, |f: String| sol_steps(&f));
+
+    // (problem, size, weight, our steps, best steps, points, gain)
+    let mut rows = Vec::with_capacity(filenames.len());
+    for (filename, res) in filenames.iter().zip(solved) {
+        match res {
+            Some((size, weight, steps)) => {
+                let best = best.get(contest::problem_key(filename)).copied();
+                let points = match (steps, best) {
+                    (Some(steps), Some(best)) => contest::points(weight, best, steps),
+                    (Some(steps), None)       => contest::points(weight, steps, steps),
+                    (None, _)                 => 0.0
+                };
+                rows.push((filename, size, weight.ceil(), steps, best, points, weight.ceil() - points));
             }
+            None => println!("{} \tunparsable", filename)
         }
     }
+    rows.sort_by(|a, b| b.6.partial_cmp(&a.6).unwrap().then_with(|| a.0.cmp(b.0)));
 
-    if interactive {
-        print_state(level, drones);
-        println!("\x1B[?1049l");
+    println!("{:<28} {:>9} {:>7} {:>7} {:>7} {:>7} {:>7}", "problem", "size", "weight", "steps", "best", "points", "gain");
+    let dash = |n: Option<usize>| n.map_or(String::from("-"), |n| n.to_string());
+    for (filename, size, weight, steps, best, points, gain) in &rows {
+        println!("{:<28} {:>9} {:>7.0} {:>7} {:>7} {:>7.0} {:>7.0}", filename, size, weight, dash(*steps), dash(*best), points, gain);
     }
-
-    let paths: Vec<&str> = drones.iter().map(|d| d.path.as_str()).collect();
-    paths.join("#")
+    let total: f64 = rows.iter().map(|r| r.5).sum();
+    let max: f64 = rows.iter().map(|r| r.2).sum();
+    println!("Expected {:.0} of {:.0} points, {} problems unsolved, {} without a best known time",
+             total, max, rows.iter().filter(|r| r.3.is_none()).count(), rows.iter().filter(|r| r.4.is_none()).count());
 }
 
-fn solve(filename: &str, interactive: bool) {
+fn solve_puzzle(filename: &str, filename_desc: &str) {
     if let Ok(contents) = fs::read_to_string(filename) {
-        let t_start = Instant::now();
-        let (mut level, mut drones) = parser::parse_level(&contents);
-        let solution = solve_impl(&mut level, &mut drones, interactive);
-        let score = solution.split("#").map(|s| Regex::new(r"[A-Z]").unwrap().find_iter(s).count()).max().unwrap();
-        println!("{} \tscore {} \ttime {} ms", filename, score, t_start.elapsed().as_millis());
-
-        let filename_sol = Regex::new(r"\.desc$").unwrap().replace(filename, ".sol");
-        let mut file = File::create(filename_sol.into_owned()).unwrap();
-        file.write_all(solution.as_bytes()).unwrap();
+        let cond = match puzzle::parse_cond(&contents) {
+            Ok(cond)   => cond,
+            Err(error) => { println!("{} \tunparsable \t{}", filename, error); return; }
+        };
+        match puzzle::solve(&cond) {
+            Ok(desc) => {
+                let problem = icfpc2019::parse_level(&desc).unwrap();
+                println!("{} \tsize {}x{} \tarea {}", filename_desc, problem.width(), problem.height(), problem.area());
+                let mut file = File::create(filename_desc).unwrap();
+                file.write_all(desc.as_bytes()).unwrap();
+            }
+            Err(reason) => println!("{} \tunsolved \t{}", filename, reason)
+        }
     } else {
         println!("Failed to read {}", filename);
     }
 }
 
-fn doall<T, F>(tasks: VecDeque<T>, threads: usize, f: F)
-    where F: Fn(T),
+fn check_puzzle(filename: &str, filename_desc: &str) {
+    match (fs::read_to_string(filename), fs::read_to_string(filename_desc)) {
+        (Ok(contents), Ok(desc)) => match puzzle::parse_cond(&contents) {
+            Ok(cond) => match puzzle::check(&cond, &desc) {
+                Ok(())      => println!("{} \tvalid", filename_desc),
+                Err(errors) => {
+                    let reasons: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                    println!("{} \tinvalid \t{}", filename_desc, reasons.join("; "));
+                }
+            },
+            Err(error) => println!("{} \tunparsable \t{}", filename, error)
+        },
+        (Err(_), _) => println!("Failed to read {}", filename),
+        (_, Err(_)) => println!("Failed to read {}", filename_desc)
+    }
+}
+
+// Runs f over tasks on a pool of threads, returns results in tasks order
+fn doall<T, R, F>(tasks: VecDeque<T>, threads: usize, f: F) -> Vec<R>
+    where F: Fn(T) -> R,
           F: Copy + Send + 'static,
-          T: Send + 'static
+          T: Send + 'static,
+          R: Send + 'static
 {
-    let m_queue = Arc::new(Mutex::new(tasks));
+    let m_queue = Arc::new(Mutex::new(tasks.into_iter().enumerate().collect::<VecDeque<_>>()));
+    let m_results = Arc::new(Mutex::new(Vec::new()));
     let mut handles = vec![];
 
-    for i in 0..threads {
+    for _ in 0..threads {
         let m_queue = Arc::clone(&m_queue);
+        let m_results = Arc::clone(&m_results);
         let handle = thread::spawn(move || loop {
             let o_task = {
                 let mut queue = m_queue.lock().unwrap();
                 queue.pop_front()
             };
-            if let Some(task) = o_task {
-                f(task);
+            if let Some((idx, task)) = o_task {
+                let result = f(task);
+                m_results.lock().unwrap().push((idx, result));
             } else {
                 break;
             }
@@ -612,31 +559,142 @@ fn doall<T, F>(tasks: VecDeque<T>, threads: usize, f: F)
     for handle in handles {
         handle.join().unwrap();
     }
+
+    let mut results = Arc::try_unwrap(m_results).ok().unwrap().into_inner().unwrap();
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode { SOLVE, VALIDATE, OPTIMIZE, REPLAY, EXPORT, PUZZLE, CHECK, BUY, RESULTS, ESTIMATE, TUNE }
+
 fn main() {
     let t_start = Instant::now();
     let args: Vec<String> = env::args().collect();
     let threads_re = Regex::new(r"--threads=([1-9][0-9]*)").unwrap();
-    let mut interactive = false;
+    let hands_re = Regex::new(r"^--hands=(line|symmetric|tshape|lookahead)(?::([1-9][0-9]*))?$").unwrap();
+    let coins_re = Regex::new(r"^--coins=([0-9]+)$").unwrap();
+    let report_re = Regex::new(r"^--report=(.+)$").unwrap();
+    let best_re = Regex::new(r"^--best=(.+)$").unwrap();
+    let strategy_re = Regex::new(r"^--strategy=(.+)$").unwrap();
+    let scorer_re = Regex::new(r"^--scorer=(.+)$").unwrap();
+    let seed_re = Regex::new(r"^--seed=([0-9]+)$").unwrap();
+    let time_limit_re = Regex::new(r"^--time-limit=([0-9]+(?:\.[0-9]+)?)$").unwrap();
+    let tune_re = Regex::new(r"^--(generations|population|sample)=([1-9][0-9]*)$").unwrap();
+    let summary_re = Regex::new(r"^--summary=(json|csv)$").unwrap();
+    let mut mode = Mode::SOLVE;
+    let mut options = Options::default();
     let mut threads = 1;
-    let mut filenames: VecDeque<String> = VecDeque::new();
-
-    for arg in args[1..].iter() {
-        if arg == "--interactive" {
-            interactive = true;
+    let mut coins = 0;
+    let mut report = String::from("buy.report");
+    let mut best = String::from("best.csv");
+    let (mut generations, mut population, mut sample) = (10, 8, None);
+    let mut time_limit = None;
+    let mut format = Summary::TEXT;
+    let mut html = false;
+    let mut tasks: VecDeque<(String, String)> = VecDeque::new();
+
+    for (i, arg) in args[1..].iter().enumerate() {
+        if i == 0 && arg == "validate" {
+            mode = Mode::VALIDATE;
+        } else if i == 0 && arg == "optimize" {
+            mode = Mode::OPTIMIZE;
+        } else if i == 0 && arg == "replay" {
+            mode = Mode::REPLAY;
+        } else if i == 0 && arg == "export" {
+            mode = Mode::EXPORT;
+        } else if arg == "--html" {
+            html = true;
+        } else if i == 0 && arg == "puzzle" {
+            mode = Mode::PUZZLE;
+        } else if i == 0 && arg == "check" {
+            mode = Mode::CHECK;
+        } else if i == 0 && arg == "buy" {
+            mode = Mode::BUY;
+        } else if i == 0 && arg == "results" {
+            mode = Mode::RESULTS;
+        } else if i == 0 && arg == "estimate" {
+            mode = Mode::ESTIMATE;
+        } else if i == 0 && arg == "tune" {
+            mode = Mode::TUNE;
+        } else if let Some(caps) = tune_re.captures(arg) {
+            let n = caps[2].parse::<usize>().unwrap();
+            match &caps[1] {
+                "generations" => generations = n,
+                "population"  => population = n,
+                _             => sample = Some(n)
+            }
+        } else if let Some(caps) = coins_re.captures(arg) {
+            coins = caps[1].parse::<usize>().unwrap();
+        } else if let Some(caps) = report_re.captures(arg) {
+            report = caps[1].to_string();
+        } else if let Some(caps) = best_re.captures(arg) {
+            best = caps[1].to_string();
+        } else if let Some(caps) = summary_re.captures(arg) {
+            format = if &caps[1] == "json" { Summary::JSON } else { Summary::CSV };
+        } else if arg == "--interactive" {
+            options.interactive = true;
+        } else if let Some(caps) = hands_re.captures(arg) {
+            options.attach = match &caps[1] {
+                "line"      => Attach::LINE,
+                "symmetric" => Attach::SYMMETRIC,
+                "tshape"    => Attach::TSHAPE,
+                _           => Attach::LOOKAHEAD(caps.get(2).map_or(20, |m| m.as_str().parse::<usize>().unwrap()))
+            };
+        } else if let Some(name) = strategy_re.captures(arg).and_then(|caps| STRATEGIES.iter().find(|s| **s == &caps[1])) {
+            options.strategy = name;
+        } else if let Some(caps) = scorer_re.captures(arg) {
+            let scorer = fs::read_to_string(&caps[1]).map_err(|e| e.to_string())
+                .and_then(|config| icfpc2019::parse_scorer(&config).map_err(|e| e.to_string()));
+            match scorer {
+                Ok(scorer) => options.scorer = scorer,
+                Err(error) => { eprintln!("{}: {}", &caps[1], error); process::exit(1); }
+            }
+        } else if let Some(caps) = seed_re.captures(arg) {
+            options.seed = caps[1].parse::<u64>().unwrap();
+        } else if let Some(caps) = time_limit_re.captures(arg) {
+            time_limit = Some(Duration::from_secs_f64(caps[1].parse::<f64>().unwrap()));
         } else if let Some(caps) = threads_re.captures(arg) {
             threads = caps.get(1).unwrap().as_str().parse::<isize>().unwrap() as usize;
+        } else if arg.ends_with(".cond") && (mode == Mode::PUZZLE || mode == Mode::CHECK) {
+            tasks.push_back((arg.clone(), desc_filename(arg)));
+        } else if arg.ends_with(".desc") && mode == Mode::CHECK && !tasks.is_empty() {
+            tasks.back_mut().unwrap().1 = arg.clone();
         } else if arg.ends_with(".desc") {
-            filenames.push_back(arg.clone());
+            tasks.push_back((arg.clone(), sol_filename(arg)));
+        } else if arg.ends_with(".sol") && matches!(mode, Mode::VALIDATE | Mode::OPTIMIZE | Mode::REPLAY | Mode::EXPORT) && !tasks.is_empty() {
+            tasks.back_mut().unwrap().1 = arg.clone();
         } else {
-            panic!("cargo run --release [--interactive] [--threads=N] <path/to/problem.desc>");
+            panic!("cargo run --release [validate|optimize|replay|export|puzzle|check|buy|results|estimate|tune] [--interactive] [--html] [--threads=N] [--hands=line|symmetric|tshape|lookahead[:N]] [--strategy={}] [--scorer=FILE] [--seed=N] [--time-limit=SECS] [--coins=N] [--report=FILE] [--best=FILE] [--generations=N] [--population=N] [--sample=N] [--summary=json|csv] <path/to/problem.desc> [path/to/problem.sol] | <path/to/puzzle.cond> [path/to/puzzle.desc]", STRATEGIES.join("|"));
         }
     }
 
-    let tasks = filenames.len();
-    doall(filenames, threads, move |f| solve(&f, interactive));
-    if tasks > 1 {
-        println!("Finished {} tasks in {} ms", tasks, t_start.elapsed().as_millis());
+    // mcts spends the time limit searching in one run instead of restarting
+    if options.strategy == "mcts" { options.budget = time_limit.take(); }
+
+    let count = tasks.len();
+    match mode {
+        Mode::SOLVE    => {
+            if format == Summary::CSV { println!("{}", CSV_HEADER); }
+            doall(tasks, threads, move |(f, _)| solve(&f, options, time_limit, format));
+        }
+        Mode::VALIDATE => { doall(tasks, threads, move |(f, sol)| validate(&f, &sol)); }
+        Mode::OPTIMIZE => { doall(tasks, threads, move |(f, sol)| optimize(&f, &sol)); }
+        Mode::REPLAY   => {
+            for (f, sol) in tasks {
+                replay(&f, &sol);
+            }
+        }
+        Mode::EXPORT   => { doall(tasks, threads, move |(f, sol)| export(&f, &sol, html)); }
+        Mode::PUZZLE   => { doall(tasks, threads, move |(f, desc)| solve_puzzle(&f, &desc)); }
+        Mode::CHECK    => { doall(tasks, threads, move |(f, desc)| check_puzzle(&f, &desc)); }
+        Mode::BUY      => buy(tasks.into_iter().map(|(f, _)| f).collect(), coins, threads, options, &report),
+        Mode::RESULTS  => results(tasks.into_iter().map(|(f, _)| f).collect()),
+        Mode::ESTIMATE => estimate(tasks.into_iter().map(|(f, _)| f).collect(), threads, &best),
+        Mode::TUNE     => tune(tasks.into_iter().map(|(f, _)| f).collect(), sample, generations, population, threads, options)
+    }
+    if count > 1 && format == Summary::TEXT && !matches!(mode, Mode::RESULTS | Mode::ESTIMATE | Mode::TUNE) {
+        println!("Finished {} tasks in {} ms", count, t_start.elapsed().as_millis());
     }
 }
+
@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use fnv::FnvHashSet;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use crate::{ Bonus, Action, Options, Level, Drone, get_or, step };
+use crate::strategy::{ Decision, Strategy, GreedyZoneStrategy, GreedyPlanner };
+
+// Maps with more cells are solved greedily
+const MAX_AREA: isize = 50 * 50;
+// Time for the whole problem when options don't say
+const BUDGET: Duration = Duration::from_secs(10);
+// Chance that a rollout step is a random move instead of a greedy one
+const EPSILON: f64 = 0.02;
+// Weight of the exploration term, in time steps
+const EXPLORATION: f64 = 2.;
+
+// Level and drones as solve_impl has them before drones[idx] decides
+#[derive(Clone)]
+struct Game {
+    level:  Level,
+    drones: Vec<Drone>,
+    idx:    usize,
+    acting: usize, // drones in this time step, clones made during it start with the next one
+    time:   usize
+}
+
+impl Game {
+    fn new(level: &Level, drones: &[Drone], idx: usize, acting: usize, time: usize) -> Game {
+        Game { level: level.clone(), drones: drones.to_vec(), idx, acting, time }
+    }
+
+    fn done(&self) -> bool {
+        self.level.empty == 0
+    }
+
+    // Applies the decision of drones[idx] and gets the next drone ready to decide
+    fn apply(&mut self, decision: Decision) {
+        if let Some(clone) = self.drones[self.idx].execute(decision, &mut self.level) {
+            self.drones.push(clone);
+        }
+        self.idx += 1;
+        if self.idx == self.acting {
+            self.idx = 0;
+            self.acting = self.drones.len();
+            self.time += 1;
+        }
+        if !self.done() {
+            self.drones[self.idx].collect(&mut self.level);
+            self.drones[self.idx].wear_off();
+        }
+    }
+
+    // Single steps drones[idx] can take
+    fn moves(&self) -> Vec<Decision> {
+        let drone = &self.drones[self.idx];
+        let actions = [Action::LEFT, Action::RIGHT, Action::UP, Action::DOWN, Action::CLOCKWISE, Action::COUNTERCLOCKWISE, Action::JUMP0, Action::JUMP1, Action::JUMP2];
+        actions.iter()
+            .filter(|a| step(&self.level, None, &drone.pos, drone.orientation, a, drone.wheels > 0, drone.drill > 0, &FnvHashSet::default()).is_some())
+            .map(|a| Decision::PLAN(drone.zone, VecDeque::from(vec![*a])))
+            .collect()
+    }
+
+    // Everything drones[idx] can do: moves, turns, jumps, boosters and attaching a hand anywhere
+    fn decisions(&self) -> Vec<Decision> {
+        let (level, drone) = (&self.level, &self.drones[self.idx]);
+        let has = |bonus| get_or(&level.collected, &bonus, 0) > 0;
+        let mut res = self.moves();
+        if has(Bonus::CLONE) && level.spawns.contains(&drone.pos) { res.push(Decision::CLONE); }
+        // a plan made without wheels would run into walls with them
+        if has(Bonus::WHEELS) && drone.wheels == 0 && drone.plan.is_empty() { res.push(Decision::WHEELS); }
+        if has(Bonus::DRILL) && drone.drill == 0 { res.push(Decision::DRILL); }
+        if has(Bonus::HAND) { res.extend(drone.attachable().into_iter().map(Decision::HAND)); }
+        if has(Bonus::TELEPORT) && level.beakons.iter().all(|b| (b.x - drone.pos.x).abs() + (b.y - drone.pos.y).abs() >= 50) {
+            res.push(Decision::BEAKON);
+        }
+        res
+    }
+}
+
+// Plays greedily to the end, with random moves now and then unless seed is 0.
+// Decisions are appended to path, returns the time step everything is wrapped at
+fn rollout(mut game: Game, path: &mut Vec<Decision>, options: &Options, seed: u64, rng: &mut Pcg32) -> usize {
+    let mut greedy = GreedyZoneStrategy::new(options, seed, false, Box::new(GreedyPlanner));
+    while !game.done() {
+        let decision = if seed != 0 && rng.gen_bool(EPSILON) {
+            let mut moves = game.moves();
+            moves.swap_remove(rng.gen_range(0, moves.len()))
+        } else {
+            greedy.decide(&game.level, &game.drones, game.idx)
+        };
+        path.push(decision.clone());
+        game.apply(decision);
+    }
+    game.time
+}
+
+// A complete solution from the current state on: the decisions, and which
+// children they are for as long as they're in the tree
+struct Line {
+    time:      usize,
+    children:  VecDeque<usize>,
+    decisions: VecDeque<Decision>
+}
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(Decision, Node)>,
+    untried:  Option<Vec<Decision>>, // filled on the first visit
+    visits:   f64,
+    total:    f64                    // sum of finishing times of rollouts through the node
+}
+
+impl Node {
+    fn mean(&self) -> f64 {
+        self.total / self.visits
+    }
+
+    // Child with the best lower confidence bound on the finishing time
+    fn select(&self) -> usize {
+        let lcb = |n: &Node| n.mean() - EXPLORATION * (self.visits.ln() / n.visits).sqrt();
+        (0..self.children.len()).min_by(|&a, &b| lcb(&self.children[a].1).partial_cmp(&lcb(&self.children[b].1)).unwrap()).unwrap()
+    }
+
+    // Selection, expansion of one untried decision, rollout and backpropagation.
+    // Tree children and decisions taken are appended to line, returns the finishing time
+    fn visit(&mut self, mut game: Game, line: &mut Line, options: &Options, rng: &mut Pcg32) -> usize {
+        let untried = self.untried.get_or_insert_with(|| if game.done() { Vec::new() } else { game.decisions() });
+        let time = if !untried.is_empty() {
+            let decision = untried.swap_remove(rng.gen_range(0, untried.len()));
+            game.apply(decision.clone());
+            line.children.push_back(self.children.len());
+            line.decisions.push_back(decision.clone());
+            let mut path = Vec::new();
+            let time = rollout(game, &mut path, options, rng.gen::<u64>() | 1, rng);
+            line.decisions.extend(path);
+            self.children.push((decision, Node { visits: 1., total: time as f64, ..Node::default() }));
+            time
+        } else if self.children.is_empty() {
+            game.time
+        } else {
+            let best = self.select();
+            let (decision, child) = &mut self.children[best];
+            game.apply(decision.clone());
+            line.children.push_back(best);
+            line.decisions.push_back(decision.clone());
+            child.visit(game, line, options, rng)
+        };
+        self.visits += 1.;
+        self.total += time as f64;
+        time
+    }
+}
+
+// Monte Carlo tree search over everything a drone can do, one drone decision per level of
+// the tree. Rollouts play greedily to the end with random moves now and then, so every one
+// of them is a complete solution: drones follow the best one found so far, starting from
+// a plain greedy one. Every decision gets a share of the budget by how many are left in
+// it. Bigger maps are wrapped greedily by zones
+pub(crate) struct MctsStrategy {
+    options: Options,
+    greedy:  GreedyZoneStrategy,
+    rng:     Pcg32,
+    start:   Option<Instant>,
+    best:    Option<Line>,
+    tree:    Node,  // for the state the best line starts at
+    acting:  usize, // drones there were when the time step began, the ones that act in it
+    time:    usize
+}
+
+impl MctsStrategy {
+    pub(crate) fn new(options: &Options) -> MctsStrategy {
+        MctsStrategy { options: *options, greedy: GreedyZoneStrategy::new(options, options.seed, true, Box::new(GreedyPlanner)),
+                       rng: Pcg32::seed_from_u64(options.seed), start: None, best: None, tree: Node::default(),
+                       acting: 0, time: 0 }
+    }
+}
+
+impl Strategy for MctsStrategy {
+    fn decide(&mut self, level: &Level, drones: &[Drone], idx: usize) -> Decision {
+        if level.width * level.height > MAX_AREA {
+            return self.greedy.decide(level, drones, idx);
+        }
+        // solve_impl asks drones[0] first in every time step, clones made during it wait for the next one
+        if idx == 0 {
+            if self.start.is_some() { self.time += 1; }
+            self.acting = drones.len();
+        }
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let game = Game::new(level, drones, idx, self.acting, self.time);
+        let (options, rng) = (&self.options, &mut self.rng);
+        let best = self.best.get_or_insert_with(|| {
+            let mut path = Vec::new();
+            let time = rollout(game.clone(), &mut path, options, options.seed, rng);
+            Line { time, children: VecDeque::new(), decisions: path.into() }
+        });
+
+        let left = (options.budget.unwrap_or(BUDGET)).saturating_sub(start.elapsed());
+        let slice = left.div_f64(best.decisions.len() as f64);
+        let t_start = Instant::now();
+        while t_start.elapsed() < slice {
+            let mut line = Line { time: 0, children: VecDeque::new(), decisions: VecDeque::new() };
+            line.time = self.tree.visit(game.clone(), &mut line, options, rng);
+            if line.time < best.time { *best = line; }
+        }
+
+        // the subtree of the decision taken is kept if it's in the tree
+        let tree = std::mem::take(&mut self.tree);
+        self.tree = match best.children.pop_front() {
+            Some(child) => tree.children.into_iter().nth(child).unwrap().1,
+            None        => Node::default()
+        };
+        best.decisions.pop_front().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ Point, parse_level_with };
+
+    #[test]
+    fn acting_drones() {
+        let problem = parse_level_with("(0,0),(6,0),(6,3),(0,3)#(0,0)##X(0,0)", "CC").unwrap();
+        let mut game = Game::new(&problem.level, &problem.drones, 0, 1, 0);
+        let right = |game: &Game| Decision::PLAN(game.drones[game.idx].zone, VecDeque::from(vec![Action::RIGHT]));
+        // the clone made in a time step acts from the next one on, after drones that were there before
+        game.apply(Decision::CLONE);
+        assert_eq!((game.idx, game.acting, game.time), (0, 2, 1));
+        game.apply(Decision::CLONE);
+        assert_eq!((game.idx, game.acting, game.time), (1, 2, 1));
+        game.apply(right(&game));
+        assert_eq!((game.idx, game.acting, game.time), (0, 3, 2));
+        assert_eq!(game.drones.iter().map(|d| d.pos).collect::<Vec<_>>(), vec![Point::new(0, 0), Point::new(1, 0), Point::new(0, 0)]);
+    }
+}
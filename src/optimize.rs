@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use fnv::{FnvHashMap, FnvHashSet};
+use crate::{ Point, Cell, Orientation, Level, Problem, SolAction, Solution, Invalid, is_reaching, validate };
+use crate::validator::Replay;
+
+// Longest stretch of a path replaced at once
+const MAX_WINDOW: usize = 50;
+// Cells a replacement path is checked to wrap instead of the stretch it replaces
+const MAX_RESCUED: usize = 8;
+
+// One action of a drone as replayed
+struct Step {
+    pos:     Point,       // after the action
+    hands:   Vec<Point>,
+    orientation: Orientation,
+    plain:   bool,        // a move or wait without wheels or drill, not picking up a booster
+    covers:  Vec<Point>   // cells to wrap the hands reach after the action
+}
+
+struct Trace {
+    starts: Vec<Point>,              // position of every drone before its first action
+    steps:  Vec<Vec<Step>>,
+    counts: FnvHashMap<Point, usize> // steps of all drones covering a cell
+}
+
+// Stretch start..end of a drone's path replaced with moves
+struct Edit {
+    start:  usize,
+    end:    usize,
+    moves:  Vec<SolAction>,
+    covers: Vec<Point>
+}
+
+fn delta(action: SolAction) -> (isize, isize) {
+    match action {
+        SolAction::UP    => ( 0,  1),
+        SolAction::RIGHT => ( 1,  0),
+        SolAction::DOWN  => ( 0, -1),
+        SolAction::LEFT  => (-1,  0),
+        _                => ( 0,  0)
+    }
+}
+
+// Cells to wrap on the initial map that hands reach from pos, on the map as it is now
+fn covers(initial: &Level, level: &Level, hands: &[Point], orientation: Orientation, pos: &Point) -> Vec<Point> {
+    let mut res = Vec::with_capacity(hands.len());
+    for hand in hands {
+        let offset = orientation.rotate(hand);
+        let p = Point::new(pos.x + offset.x, pos.y + offset.y);
+        if initial.valid(p.x, p.y) && initial.get_cell(p.x, p.y) == Cell::EMPTY && is_reaching(level, pos, &offset) {
+            res.push(p);
+        }
+    }
+    res
+}
+
+fn trace(problem: &Problem, solution: &Solution) -> Result<Trace, Invalid> {
+    let timeline = solution.timeline();
+    let mut replay = Replay::new(problem.level.clone(), problem.drones.clone(), solution);
+    let mut trace = Trace { starts: Vec::new(), steps: solution.paths.iter().map(|_| Vec::new()).collect(), counts: FnvHashMap::default() };
+    for drone in &replay.drones {
+        trace.starts.push(drone.pos);
+        for p in covers(&problem.level, &replay.level, &drone.hands, drone.orientation, &drone.pos) {
+            *trace.counts.entry(p).or_insert(0) += 1;
+        }
+    }
+    while !replay.done() {
+        let picked = replay.picked_up.len();
+        replay.advance()?;
+        for (idx, timing) in timeline.iter().enumerate().take(replay.drones.len()) {
+            let timing = match timing { Some(t) if replay.step > t.spawn && replay.step <= t.finish => t, _ => continue };
+            let action = solution.paths[idx][replay.step - timing.spawn - 1];
+            let drone = &replay.drones[idx];
+            let plain = matches!(action, SolAction::UP | SolAction::RIGHT | SolAction::DOWN | SolAction::LEFT | SolAction::WAIT)
+                        && drone.wheels == 0 && drone.drill == 0 && !replay.picked_up[picked..].iter().any(|(p, _)| *p == drone.pos);
+            let covers = covers(&problem.level, &replay.level, &drone.hands, drone.orientation, &drone.pos);
+            for p in &covers {
+                *trace.counts.entry(*p).or_insert(0) += 1;
+            }
+            trace.steps[idx].push(Step { pos: drone.pos, hands: drone.hands.clone(), orientation: drone.orientation, plain, covers });
+        }
+        while trace.starts.len() < replay.drones.len() {
+            trace.starts.push(replay.drones[trace.starts.len()].pos);
+        }
+    }
+    replay.run()?;
+    Ok(trace)
+}
+
+// Plain moves from `from` on the initial map, up to limit of them: distance and last move to every cell reached
+fn distances(level: &Level, from: Point, limit: usize) -> FnvHashMap<Point, (usize, SolAction)> {
+    let mut seen: FnvHashMap<Point, (usize, SolAction)> = FnvHashMap::default();
+    let mut queue: VecDeque<Point> = VecDeque::new();
+    seen.insert(from, (0, SolAction::WAIT));
+    queue.push_back(from);
+    while let Some(pos) = queue.pop_front() {
+        let dist = seen[&pos].0;
+        if dist >= limit { continue; }
+        for &action in &[SolAction::UP, SolAction::RIGHT, SolAction::DOWN, SolAction::LEFT] {
+            let (dx, dy) = delta(action);
+            let to = Point::new(pos.x + dx, pos.y + dy);
+            if level.in_map(to.x, to.y) && level.walkable(to.x, to.y) && !seen.contains_key(&to) {
+                seen.insert(to, (dist + 1, action));
+                queue.push_back(to);
+            }
+        }
+    }
+    seen
+}
+
+fn path_to(seen: &FnvHashMap<Point, (usize, SolAction)>, to: Point) -> Vec<SolAction> {
+    let mut moves = Vec::new();
+    let mut pos = to;
+    while let Some(&(dist, action)) = seen.get(&pos) {
+        if dist == 0 { break; }
+        let (dx, dy) = delta(action);
+        moves.push(action);
+        pos = Point::new(pos.x - dx, pos.y - dy);
+    }
+    moves.reverse();
+    moves
+}
+
+fn path_covers(level: &Level, step: &Step, from: Point, moves: &[SolAction]) -> Vec<Point> {
+    let mut res = Vec::new();
+    let mut pos = from;
+    for &action in moves {
+        let (dx, dy) = delta(action);
+        pos = Point::new(pos.x + dx, pos.y + dy);
+        res.extend(covers(level, level, &step.hands, step.orientation, &pos));
+    }
+    res
+}
+
+// Biggest saving from a stretch of plain steps starting at i: cells it covers
+// must be covered by other steps or by the shorter path that replaces it.
+// A stretch at the end of the path is just dropped
+fn best_edit(level: &Level, steps: &[Step], start: Point, i: usize, counts: &FnvHashMap<Point, usize>) -> Option<Edit> {
+    let from = if i == 0 { start } else { steps[i - 1].pos };
+    let end = (i..steps.len().min(i + MAX_WINDOW)).find(|&j| !steps[j].plain).unwrap_or(steps.len().min(i + MAX_WINDOW));
+    let manhattan = |p: &Point| ((p.x - from.x).abs() + (p.y - from.y).abs()) as usize;
+    let limit = (i + 1..=end).filter(|&j| j == steps.len() || manhattan(&steps[j - 1].pos) < j - i).max()?;
+    let seen = distances(level, from, limit - i - 1);
+
+    let mut window: FnvHashMap<Point, usize> = FnvHashMap::default();
+    let mut unique: FnvHashSet<Point> = FnvHashSet::default();
+    let mut best: Option<(usize, Edit)> = None;
+    for j in i + 1..=limit {
+        for p in &steps[j - 1].covers {
+            let w = window.entry(*p).or_insert(0);
+            *w += 1;
+            if *w == counts[p] { unique.insert(*p); }
+        }
+        let candidate = if j == steps.len() {
+            Some((j - i, Vec::new(), Vec::new())).filter(|_| unique.is_empty())
+        } else {
+            match seen.get(&steps[j - 1].pos) {
+                Some(&(dist, _)) if dist < j - i && unique.len() <= MAX_RESCUED => {
+                    let moves = path_to(&seen, steps[j - 1].pos);
+                    let covers = path_covers(level, &steps[i], from, &moves);
+                    Some((j - i - dist, moves, covers)).filter(|(_, _, covers)| unique.iter().all(|p| covers.contains(p)))
+                }
+                _ => None
+            }
+        };
+        if let Some((gain, moves, covers)) = candidate {
+            if best.as_ref().is_none_or(|(g, _)| gain > *g) {
+                best = Some((gain, Edit { start: i, end: j, moves, covers }));
+            }
+        }
+    }
+    best.map(|(_, edit)| edit)
+}
+
+// Edits along the path of a drone, each one taking into account the ones before it
+fn edits(level: &Level, trace: &Trace, idx: usize) -> Vec<Edit> {
+    let steps = &trace.steps[idx];
+    let mut counts = trace.counts.clone();
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < steps.len() {
+        match best_edit(level, steps, trace.starts[idx], i, &counts) {
+            Some(edit) => {
+                for p in steps[edit.start..edit.end].iter().flat_map(|s| &s.covers) {
+                    *counts.get_mut(p).unwrap() -= 1;
+                }
+                for p in &edit.covers {
+                    *counts.entry(*p).or_insert(0) += 1;
+                }
+                i = edit.end;
+                res.push(edit);
+            }
+            None => i += 1
+        }
+    }
+    res
+}
+
+fn apply(path: &[SolAction], edits: &[&Edit]) -> Vec<SolAction> {
+    let mut res = Vec::with_capacity(path.len());
+    let mut i = 0;
+    for edit in edits {
+        res.extend_from_slice(&path[i..edit.start]);
+        res.extend_from_slice(&edit.moves);
+        i = edit.end;
+    }
+    res.extend_from_slice(&path[i..]);
+    res
+}
+
+// Shortens a valid solution by cutting stretches of plain moves whose cells get wrapped
+// anyway, or replacing them with the shortest path between their ends. Moves with wheels
+// or drill on are left alone, shortening them would stretch the boosters over later moves.
+// Edits that break the solution (e.g. a booster another drone picks up is needed earlier)
+// are dropped, and so are ones that don't take fewer steps in total, like shortening
+// a drone that isn't the last one to finish. Repeats while the solution gets shorter.
+// None when no edit did
+pub fn optimize(problem: &Problem, solution: &Solution) -> Result<Option<Solution>, Invalid> {
+    let mut best = solution.clone();
+    let initial = validate(problem, &best)?;
+    let mut score = initial;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for idx in 0..best.paths.len() {
+            let trace = trace(problem, &best)?;
+            let edits = edits(&problem.level, &trace, idx);
+            if edits.is_empty() { continue; }
+            // all edits of the drone at once, or one by one if they don't work together
+            let mut tries: Vec<Vec<&Edit>> = vec![edits.iter().collect()];
+            if edits.len() > 1 {
+                tries.extend(edits.iter().rev().map(|e| vec![e]));
+            }
+            for edits in tries {
+                let mut candidate = best.clone();
+                candidate.paths[idx] = apply(&best.paths[idx], &edits);
+                match validate(problem, &candidate) {
+                    Ok(steps) if steps < score => {
+                        best = candidate;
+                        score = steps;
+                        improved = true;
+                        if edits.len() > 1 { break; }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(Some(best).filter(|_| score < initial))
+}
@@ -1,41 +1,244 @@
-use lazy_static::lazy_static;
-use regex::{Regex, Captures};
-use std::cmp;
+use std::{cmp, fmt};
 use std::collections::{VecDeque};
 use fnv::{FnvHashMap, FnvHashSet};
-use crate::{ Point, Line, Cell, Bonus, Drone, Level, UNDECIDED_ZONE };
+use crate::{ Point, Cell, Bonus, Drone, Level, UNDECIDED_ZONE, MAX_ZONES };
+use crate::puzzle::{ Cond, Candidate };
 use rand::{Rng, SeedableRng};
-use rand_pcg::Pcg32;
 
-lazy_static! {
-    static ref POINT_RE: Regex = Regex::new(r"\((?P<X>-?\d+),(?P<Y>-?\d+)\)").unwrap();
-    static ref BONUS_RE: Regex = Regex::new(r"(?P<P>[BFLRC])\((?P<X>-?\d+),(?P<Y>-?\d+)\)").unwrap();
-    static ref SPAWN_RE: Regex = Regex::new(r"X\((?P<X>-?\d+),(?P<Y>-?\d+)\)").unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fragment { CONTOUR, START, OBSTACLES, BOOSTERS, BOUGHT, PARAMS, INCLUDE, EXCLUDE } // last three of .cond puzzles
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    Fragments(usize),             // number of #-separated fragments isn't 4
+    Expected(&'static str),
+    Vertices(usize),              // polygon with less than 4 vertices
+    NotRectilinear(Point, Point), // edge that isn't horizontal or vertical
+    UnpairedWalls(isize),         // row where walls don't pair up into a polygon
+    Outside(Point),               // point off the map or on an obstacle
+    UnknownBooster(char),
+    Empty                         // nothing to wrap
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset:   usize, // bytes from the start of the file
+    pub fragment: Fragment,
+    pub reason:   Reason
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Fragments(count)      => write!(f, "expected 4 fragments separated by #, got {}", count),
+            Reason::Expected(what)        => write!(f, "expected {}", what),
+            Reason::Vertices(count)       => write!(f, "polygon has {} vertices, at least 4 needed", count),
+            Reason::NotRectilinear(a, b)  => write!(f, "edge ({},{})-({},{}) is not horizontal or vertical", a.x, a.y, b.x, b.y),
+            Reason::UnpairedWalls(y)      => write!(f, "walls in row {} don't pair up", y),
+            Reason::Outside(p)            => write!(f, "({},{}) is not a free cell of the map", p.x, p.y),
+            Reason::UnknownBooster(c)     => write!(f, "unknown booster {:?}", c),
+            Reason::Empty                 => write!(f, "map has no cells to wrap")
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} at byte {}: {}", self.fragment, self.offset, self.reason)
+    }
+}
+
+// Reads one fragment of a .desc file, keeping track of absolute offsets
+struct Scanner<'a> {
+    file:     &'a [u8],
+    pos:      usize,
+    end:      usize,
+    fragment: Fragment
+}
+
+impl<'a> Scanner<'a> {
+    fn new(file: &'a str, from: usize, to: usize, fragment: Fragment) -> Scanner<'a> {
+        Scanner { file: file.as_bytes(), pos: from, end: to, fragment }
+    }
+
+    fn error_at(&self, offset: usize, reason: Reason) -> ParseError {
+        ParseError { offset, fragment: self.fragment, reason }
+    }
+
+    fn error(&self, reason: Reason) -> ParseError {
+        self.error_at(self.pos, reason)
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    fn peek(&self) -> Option<u8> {
+        if self.done() { None } else { Some(self.file[self.pos]) }
+    }
+
+    fn skip(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) { self.pos += 1; true } else { false }
+    }
+
+    fn expect(&mut self, c: u8, what: &'static str) -> Result<(), ParseError> {
+        if self.skip(c) { Ok(()) } else { Err(self.error(Reason::Expected(what))) }
+    }
+
+    fn number(&mut self) -> Result<isize, ParseError> {
+        let from = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) { self.pos += 1; }
+        std::str::from_utf8(&self.file[from..self.pos]).unwrap().parse::<isize>()
+            .map_err(|_| self.error_at(from, Reason::Expected("non-negative number")))
+    }
+
+    // (x,y)
+    fn point(&mut self) -> Result<(usize, Point), ParseError> {
+        let from = self.pos;
+        self.expect(b'(', "(")?;
+        let x = self.number()?;
+        self.expect(b',', ",")?;
+        let y = self.number()?;
+        self.expect(b')', ")")?;
+        Ok((from, Point::new(x, y)))
+    }
+
+    // (x,y),(x,y),... up to ; or the end of fragment
+    fn points(&mut self) -> Result<Vec<(usize, Point)>, ParseError> {
+        let mut points = vec![self.point()?];
+        while self.skip(b',') {
+            points.push(self.point()?);
+        }
+        Ok(points)
+    }
+
+    // Closed rectilinear polygon
+    fn polygon(&mut self) -> Result<Vec<(usize, Point)>, ParseError> {
+        let from = self.pos;
+        let points = self.points()?;
+        if points.len() < 4 {
+            return Err(self.error_at(from, Reason::Vertices(points.len())));
+        }
+        for (i, &(offset, a)) in points.iter().enumerate() {
+            let (_, b) = points[(i + 1) % points.len()];
+            if (a.x == b.x) == (a.y == b.y) {
+                return Err(self.error_at(offset, Reason::NotRectilinear(a, b)));
+            }
+        }
+        Ok(points)
+    }
+
+    // B(x,y);X(x,y);... as offset, letter and position
+    fn items(&mut self) -> Result<Vec<(usize, char, Point)>, ParseError> {
+        let mut items: Vec<(usize, char, Point)> = Vec::new();
+        while !self.done() {
+            let offset = self.pos;
+            let letter = self.peek().unwrap() as char;
+            if letter != 'X' && bonus(letter).is_none() {
+                return Err(self.error(Reason::UnknownBooster(letter)));
+            }
+            self.pos += 1;
+            let (_, pos) = self.point()?;
+            items.push((offset, letter, pos));
+            if !self.done() { self.expect(b';', ";")?; }
+        }
+        Ok(items)
+    }
+
+    fn end(&self, what: &'static str) -> Result<(), ParseError> {
+        if self.done() { Ok(()) } else { Err(self.error(Reason::Expected(what))) }
+    }
 }
 
 fn grid_idx(x: isize, y: isize, width: isize) -> usize {
     (x + y * width) as usize
 }
 
-fn parse_point(s: &str) -> Point {
-    let captures = POINT_RE.captures(s).unwrap();
-    Point::new(captures["X"].parse::<isize>().unwrap(), captures["Y"].parse::<isize>().unwrap())
+fn bonus(letter: char) -> Option<Bonus> {
+    match letter {
+        'B' => Some(Bonus::HAND),
+        'F' => Some(Bonus::WHEELS),
+        'L' => Some(Bonus::DRILL),
+        'R' => Some(Bonus::TELEPORT),
+        'C' => Some(Bonus::CLONE),
+        _   => None
+    }
+}
+
+// Boosters bought in advance, one letter each: "CCB"
+pub fn parse_buy(file: &str) -> Result<FnvHashMap<Bonus, usize>, ParseError> {
+    let mut bought: FnvHashMap<Bonus, usize> = FnvHashMap::default();
+    for (offset, letter) in file.char_indices().filter(|(_, c)| !c.is_whitespace()) {
+        let bonus = bonus(letter).ok_or(ParseError { offset, fragment: Fragment::BOUGHT, reason: Reason::UnknownBooster(letter) })?;
+        *bought.entry(bonus).or_insert(0) += 1;
+    }
+    Ok(bought)
+}
+
+// bNum,eNum,tSize,vMin,vMax,mNum,fNum,dNum,rNum,cNum,xNum#iSqs#oSqs
+pub fn parse_cond(file: &str) -> Result<Cond, ParseError> {
+    let file = file.trim_end();
+    let names = [Fragment::PARAMS, Fragment::INCLUDE, Fragment::EXCLUDE];
+    let mut fragments: Vec<(usize, usize)> = Vec::with_capacity(3);
+    let mut from = 0;
+    for (idx, _) in file.match_indices('#').chain(std::iter::once((file.len(), ""))) {
+        if fragments.len() == 3 {
+            return Err(ParseError { offset: from - 1, fragment: Fragment::EXCLUDE, reason: Reason::Expected("end of file") });
+        }
+        fragments.push((from, idx));
+        from = idx + 1;
+    }
+    if fragments.len() < 3 {
+        return Err(ParseError { offset: file.len(), fragment: names[fragments.len()], reason: Reason::Expected("#") });
+    }
+
+    let mut params_sc = Scanner::new(file, fragments[0].0, fragments[0].1, Fragment::PARAMS);
+    let mut params: Vec<usize> = Vec::with_capacity(11);
+    for i in 0..11 {
+        if i > 0 { params_sc.expect(b',', ",")?; }
+        params.push(params_sc.number()? as usize);
+    }
+    params_sc.end("end of parameters")?;
+
+    let mut squares = Vec::with_capacity(2);
+    for (&(from, to), &fragment) in fragments[1..].iter().zip(names[1..].iter()) {
+        let mut squares_sc = Scanner::new(file, from, to, fragment);
+        let points = if squares_sc.done() { Vec::new() } else { squares_sc.points()? };
+        squares_sc.end("end of squares")?;
+        squares.push(vertices(&points));
+    }
+    let exclude = squares.pop().unwrap();
+    let include = squares.pop().unwrap();
+
+    let mut boosters = [0; 6];
+    boosters.copy_from_slice(&params[5..11]);
+    Ok(Cond { block: params[0], epoch: params[1], size: params[2] as isize, v_min: params[3], v_max: params[4], boosters, include, exclude })
 }
 
-fn parse_bonus(captures: Captures) -> (Point, Bonus) {
-    (Point::new(captures["X"].parse::<isize>().unwrap(), captures["Y"].parse::<isize>().unwrap()),
-     match &captures["P"] {
-         "B" => { Bonus::HAND }
-         "F" => { Bonus::WHEELS }
-         "L" => { Bonus::DRILL }
-         "R" => { Bonus::TELEPORT }
-         "C" => { Bonus::CLONE }
-         _   => panic!("Unknown bonus")
-     })
+// A puzzle solution in .desc format, checked for syntax only. Puzzle maps have no obstacles
+pub(crate) fn parse_puzzle(file: &str) -> Result<Candidate, ParseError> {
+    let file = file.trim_end();
+    let fragments = desc_fragments(file)?;
+
+    let mut contour_sc = Scanner::new(file, fragments[0].0, fragments[0].1, Fragment::CONTOUR);
+    let contour = contour_sc.points()?;
+    if contour.len() < 4 {
+        return Err(contour_sc.error_at(fragments[0].0, Reason::Vertices(contour.len())));
+    }
+    contour_sc.end("end of contour")?;
+
+    let mut start_sc = Scanner::new(file, fragments[1].0, fragments[1].1, Fragment::START);
+    let (_, start) = start_sc.point()?;
+    start_sc.end("end of start position")?;
+
+    Scanner::new(file, fragments[2].0, fragments[2].1, Fragment::OBSTACLES).end("no obstacles")?;
+
+    let mut boosters_sc = Scanner::new(file, fragments[3].0, fragments[3].1, Fragment::BOOSTERS);
+    let items = boosters_sc.items()?;
+    Ok(Candidate { contour: vertices(&contour), start, items: items.into_iter().map(|(_, letter, p)| (letter, p)).collect() })
 }
 
-fn parse_contour(s: &str) -> FnvHashSet<Point> {
-    let points: Vec<Point> = POINT_RE.find_iter(s).map(|m| parse_point(m.as_str())).collect();
+pub fn contour_walls(points: &[Point]) -> FnvHashSet<Point> {
     let mut walls: FnvHashSet<Point> = FnvHashSet::with_capacity_and_hasher(points.len(), Default::default());
     for (i, &p1) in points.iter().enumerate() {
         let p2 = points[(i+1) % points.len()];
@@ -48,12 +251,6 @@ fn parse_contour(s: &str) -> FnvHashSet<Point> {
     walls
 }
 
-fn wall_on_left(x: usize, y: usize, walls: &Vec<Line>) -> bool {
-    walls.iter().any(|l| l.from.x == x as isize
-        && l.from.y <= y as isize
-        && l.to.y >= (y + 1) as isize)
-}
-
 fn weights(grid: &[Cell], width: isize, height: isize) -> Vec<u8> {
     let mut weights: Vec<u8> = Vec::with_capacity(grid.len());
     for y in 0..height {
@@ -72,18 +269,19 @@ fn weights(grid: &[Cell], width: isize, height: isize) -> Vec<u8> {
     weights
 }
 
-fn zones(zones_count: usize, grid: &[Cell], width: isize, height: isize) -> (Vec<u8>, Vec<usize>) {
-    let len = (width * height) as usize;
+// Seed of the zones a level is parsed with
+pub const ZONES_SEED: u64 = 42;
 
-    let mut zones: Vec<u8> = Vec::with_capacity(len);
-    for i in 0..len { zones.push(UNDECIDED_ZONE); }
+pub fn zones(zones_count: usize, seed: u64, grid: &[Cell], width: isize, height: isize) -> (Vec<u8>, Vec<usize>) {
+    let len = (width * height) as usize;
 
-    let mut zones_empty: Vec<usize> = Vec::with_capacity(zones_count);
-    for i in 0..zones_count { zones_empty.push(0); }
+    let mut zones: Vec<u8> = vec![UNDECIDED_ZONE; len];
+    let mut zones_empty: Vec<usize> = vec![0; zones_count];
 
     let mut queue: VecDeque<(Point, u8)> = VecDeque::with_capacity(len);
-    let mut rng = rand_pcg::Pcg32::seed_from_u64(42);
-    while queue.len() < zones_count {
+    let mut rng = rand_pcg::Pcg32::seed_from_u64(seed);
+    let seeds = cmp::min(zones_count, grid.iter().filter(|&&c| c == Cell::EMPTY).count());
+    while queue.len() < seeds {
         let x = rng.gen_range(0, width);
         let y = rng.gen_range(0, height);
         let idx = grid_idx(x, y, width);
@@ -108,11 +306,9 @@ fn zones(zones_count: usize, grid: &[Cell], width: isize, height: isize) -> (Vec
     (zones, zones_empty)
 }
 
-fn build_level(walls: &FnvHashSet<Point>, zones_count: usize) -> Level {
-    let height = walls.iter().max_by_key(|p| p.y).unwrap().y + 1;
-    let width = walls.iter().max_by_key(|p| p.x).unwrap().x;
+// Err(y) when walls in row y don't pair up
+pub fn fill(walls: &FnvHashSet<Point>, width: isize, height: isize) -> Result<Vec<Cell>, isize> {
     let mut grid = Vec::with_capacity((width * height) as usize);
-    let mut empty = 0;
     for y in 0..height {
         let mut last_cell = Cell::BLOCKED;
         for x in 0..width {
@@ -120,14 +316,20 @@ fn build_level(walls: &FnvHashSet<Point>, zones_count: usize) -> Level {
                 last_cell = if last_cell == Cell::EMPTY { Cell::BLOCKED } else { Cell::EMPTY };
             }
             grid.push(last_cell);
-            if last_cell == Cell::EMPTY { empty += 1; }
         }
-        assert_eq!(walls.contains(&Point::new(width, y)), Cell::EMPTY == last_cell);
+        if walls.contains(&Point::new(width, y)) != (Cell::EMPTY == last_cell) {
+            return Err(y);
+        }
     }
+    Ok(grid)
+}
+
+fn build_level(grid: Vec<Cell>, inside: Vec<bool>, width: isize, height: isize, zones_count: usize) -> Level {
+    let empty = grid.iter().filter(|&&c| c == Cell::EMPTY).count();
     let weights = weights(&grid, width, height);
-    let (zones, zones_empty) = zones(zones_count, &grid, width, height);
+    let (zones, zones_empty) = zones(zones_count, ZONES_SEED, &grid, width, height);
     Level {
-        grid, weights, zones, width, height, empty, zones_empty, 
+        grid, inside, weights, zones, width, height, empty, zones_empty,
         spawns:    FnvHashSet::default(),
         beakons:   Vec::new(),
         bonuses:   FnvHashMap::default(),
@@ -135,27 +337,79 @@ fn build_level(walls: &FnvHashSet<Point>, zones_count: usize) -> Level {
     }
 }
 
-pub fn parse_level(file: &str) -> (Level, Vec<Drone>) {
-    let fragments: Vec<&str> = file.split("#").collect();
-    match *fragments {
-        [walls_str, start_str, obstacles_str, bonuses_str] => {
-            let mut walls = parse_contour(walls_str);
-            for obstacle_str in obstacles_str.split(";").filter(|s| !s.is_empty()) {
-                walls.extend(parse_contour(obstacle_str));
-            }
-            let clones = Regex::new(r"C\(\d+,\d+\)").unwrap().find_iter(bonuses_str).count();
-            let mut level = build_level(&walls, clones + 1);
+fn vertices(polygon: &[(usize, Point)]) -> Vec<Point> {
+    polygon.iter().map(|&(_, p)| p).collect()
+}
 
-            for captures in BONUS_RE.captures_iter(bonuses_str) {
-                let (pos, bonus) = parse_bonus(captures);
-                level.bonuses.insert(pos, bonus);
-            }
-            for captures in SPAWN_RE.captures_iter(bonuses_str) {
-                let pos = Point::new(captures["X"].parse::<isize>().unwrap(), captures["Y"].parse::<isize>().unwrap());
-                level.spawns.insert(pos);
-            }
-            (level, vec![Drone::new(parse_point(start_str))])
+// Start and end offsets of the 4 fragments of a .desc file
+fn desc_fragments(file: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+    let mut fragments: Vec<(usize, usize)> = Vec::with_capacity(4);
+    let mut from = 0;
+    for (idx, _) in file.match_indices('#').chain(std::iter::once((file.len(), ""))) {
+        if fragments.len() == 4 {
+            return Err(ParseError { offset: from - 1, fragment: Fragment::BOOSTERS, reason: Reason::Fragments(file.split('#').count()) });
+        }
+        fragments.push((from, idx));
+        from = idx + 1;
+    }
+    if fragments.len() < 4 {
+        let fragment = [Fragment::CONTOUR, Fragment::START, Fragment::OBSTACLES, Fragment::BOOSTERS][fragments.len()];
+        return Err(ParseError { offset: file.len(), fragment, reason: Reason::Fragments(fragments.len()) });
+    }
+    Ok(fragments)
+}
+
+pub fn parse_level(file: &str, bought: &FnvHashMap<Bonus, usize>) -> Result<(Level, Vec<Drone>), ParseError> {
+    let file = file.trim_end();
+    let fragments = desc_fragments(file)?;
+    let mut contour_sc = Scanner::new(file, fragments[0].0, fragments[0].1, Fragment::CONTOUR);
+    let contour_pts = contour_sc.polygon()?;
+    contour_sc.end("end of contour")?;
+    let width = contour_pts.iter().map(|&(_, p)| p.x).max().unwrap();
+    let height = contour_pts.iter().map(|&(_, p)| p.y).max().unwrap();
+    let contour = contour_walls(&vertices(&contour_pts));
+    let inside: Vec<bool> = fill(&contour, width, height)
+        .map_err(|y| contour_sc.error_at(fragments[0].0, Reason::UnpairedWalls(y)))?
+        .iter().map(|&c| c == Cell::EMPTY).collect();
+
+    let mut obstacles_sc = Scanner::new(file, fragments[2].0, fragments[2].1, Fragment::OBSTACLES);
+    let mut walls = contour;
+    while !obstacles_sc.done() {
+        let obstacle = obstacles_sc.polygon()?;
+        if let Some(&(offset, p)) = obstacle.iter().find(|(_, p)| p.x > width || p.y > height) {
+            return Err(obstacles_sc.error_at(offset, Reason::Outside(p)));
         }
-        _ => panic!("incomplete file")
+        walls.extend(contour_walls(&vertices(&obstacle)));
+        if !obstacles_sc.done() { obstacles_sc.expect(b';', ";")?; }
+    }
+    let grid = fill(&walls, width, height)
+        .map_err(|y| obstacles_sc.error_at(fragments[2].0, Reason::UnpairedWalls(y)))?;
+
+    let mut boosters_sc = Scanner::new(file, fragments[3].0, fragments[3].1, Fragment::BOOSTERS);
+    let items = boosters_sc.items()?;
+
+    let clones = items.iter().filter(|(_, letter, _)| *letter == 'C').count();
+    let bought_clones = bought.get(&Bonus::CLONE).cloned().unwrap_or(0);
+    let mut level = build_level(grid, inside, width, height, (clones + bought_clones + 1).min(MAX_ZONES));
+    level.collected = bought.clone();
+    if level.empty == 0 {
+        return Err(contour_sc.error_at(0, Reason::Empty));
+    }
+    for (offset, letter, pos) in items {
+        if !level.walkable(pos.x, pos.y) {
+            return Err(boosters_sc.error_at(offset, Reason::Outside(pos)));
+        }
+        match bonus(letter) {
+            Some(bonus) => { level.bonuses.insert(pos, bonus); }
+            None        => { level.spawns.insert(pos); }
+        }
+    }
+
+    let mut start_sc = Scanner::new(file, fragments[1].0, fragments[1].1, Fragment::START);
+    let (offset, start) = start_sc.point()?;
+    start_sc.end("end of start position")?;
+    if !level.walkable(start.x, start.y) {
+        return Err(start_sc.error_at(offset, Reason::Outside(start)));
     }
+    Ok((level, vec![Drone::new(start)]))
 }
@@ -0,0 +1,425 @@
+use std::collections::{VecDeque};
+use std::fmt;
+use crate::{ Point, Cell };
+use crate::parser::{ ParseError, Fragment, Reason, parse_puzzle, contour_walls, fill };
+pub use crate::parser::parse_cond;
+
+pub const BOOSTERS: [char; 6] = ['B', 'F', 'L', 'R', 'C', 'X'];
+
+// 4-neighbours first, then the full ring of 8 going clockwise from north
+const NEIGHBOURS: [(isize, isize); 4] = [(0,1),(1,0),(0,-1),(-1,0)];
+const RING: [(isize, isize); 8] = [(0,1),(1,1),(1,0),(1,-1),(0,-1),(-1,-1),(-1,0),(-1,1)];
+
+pub struct Cond {
+    pub block:    usize,
+    pub epoch:    usize,
+    pub size:     isize,
+    pub v_min:    usize,
+    pub v_max:    usize,
+    pub boosters: [usize; 6], // counts, in BOOSTERS order
+    pub include:  Vec<Point>,
+    pub exclude:  Vec<Point>
+}
+
+// A candidate puzzle solution as written, before it's checked against its Cond
+pub(crate) struct Candidate {
+    pub(crate) contour: Vec<Point>,
+    pub(crate) start:   Point,
+    pub(crate) items:   Vec<(char, Point)> // boosters and spawn points
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PuzzleError {
+    Malformed(ParseError),
+    NotRectilinear(Point, Point),        // edge between these vertices is diagonal or empty
+    Collinear(Point),                    // vertex between two edges in the same direction
+    OutOfBounds(Point),
+    NotSimple(Point, Point),             // first touching pair of edges, by their starting vertices
+    Vertices { count: usize, min: usize, max: usize },
+    Area { area: usize, min: usize },
+    NotIncluded(Point),
+    NotExcluded(Point),
+    Boosters { letter: char, count: usize, expected: usize },
+    OutsideMap(char, Point)              // start ('S') or booster placed outside of the polygon
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::Malformed(error)       => write!(f, "malformed map: {}", error),
+            PuzzleError::NotRectilinear(a, b)   => write!(f, "edge {}-{} is not axis-aligned", format_point(a), format_point(b)),
+            PuzzleError::Collinear(p)           => write!(f, "vertex {} lies on a straight edge", format_point(p)),
+            PuzzleError::OutOfBounds(p)         => write!(f, "vertex {} is out of bounds", format_point(p)),
+            PuzzleError::NotSimple(a, b)        => write!(f, "edges starting at {} and {} touch", format_point(a), format_point(b)),
+            PuzzleError::Vertices { count, min, max } => write!(f, "{} vertices, expected {}..{}", count, min, max),
+            PuzzleError::Area { area, min }     => write!(f, "area {} is less than {}", area, min),
+            PuzzleError::NotIncluded(p)         => write!(f, "square {} should be inside", format_point(p)),
+            PuzzleError::NotExcluded(p)         => write!(f, "square {} should be outside", format_point(p)),
+            PuzzleError::Boosters { letter, count, expected } => write!(f, "{} boosters {}, expected {}", count, letter, expected),
+            PuzzleError::OutsideMap(letter, p)  => write!(f, "{}{} is outside of the map", letter, format_point(p))
+        }
+    }
+}
+
+// Cells forming the map. Kept simply connected and free of cells touching
+// only by a corner, so that its border is always a simple rectilinear polygon
+struct Region {
+    size:      isize,
+    cells:     Vec<bool>,
+    forbidden: Vec<bool>, // never added: excluded squares and channels leading them out of the map
+    pinned:    Vec<bool>, // never removed: included squares, boosters, start
+    count:     usize
+}
+
+impl Region {
+    fn new(size: isize) -> Region {
+        let len = (size * size) as usize;
+        Region { size, cells: vec![false; len], forbidden: vec![false; len], pinned: vec![false; len], count: 0 }
+    }
+
+    fn valid(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.size && y >= 0 && y < self.size
+    }
+
+    fn idx(&self, x: isize, y: isize) -> usize {
+        (x + y * self.size) as usize
+    }
+
+    fn get(&self, x: isize, y: isize) -> bool {
+        self.valid(x, y) && self.cells[self.idx(x, y)]
+    }
+
+    // no 2x2 block around (x,y) has its cells set diagonally
+    fn well_composed(&self, x: isize, y: isize) -> bool {
+        [(-1,-1),(-1,0),(0,-1),(0,0)].iter().all(|(dx, dy)| {
+            let (x0, y0) = (x + dx, y + dy);
+            let (a, b, c, d) = (self.get(x0, y0), self.get(x0 + 1, y0), self.get(x0, y0 + 1), self.get(x0 + 1, y0 + 1));
+            !(a == d && b == c && a != b)
+        })
+    }
+
+    // Number of runs of set cells around (x,y) touching it by a side. Adding or
+    // removing (x,y) keeps the region connected and hole-free only when it's 1
+    fn runs(&self, x: isize, y: isize) -> usize {
+        let ring: Vec<bool> = RING.iter().map(|(dx, dy)| self.get(x + dx, y + dy)).collect();
+        if ring.iter().all(|&c| c) { return 1; }
+        let start = ring.iter().position(|&c| !c).unwrap();
+        let mut runs = 0;
+        let mut side = false;
+        for i in 1..=8 {
+            let j = (start + i) % 8;
+            if ring[j] {
+                side = side || j % 2 == 0;
+            } else {
+                if side { runs += 1; }
+                side = false;
+            }
+        }
+        runs
+    }
+
+    fn can_add(&self, x: isize, y: isize) -> bool {
+        self.valid(x, y) && !self.get(x, y) && !self.forbidden[self.idx(x, y)] && (self.count == 0 || self.runs(x, y) == 1)
+    }
+
+    fn can_remove(&self, x: isize, y: isize) -> bool {
+        self.get(x, y) && !self.pinned[self.idx(x, y)] && self.count > 1
+            && NEIGHBOURS.iter().any(|(dx, dy)| !self.get(x + dx, y + dy))
+            && self.runs(x, y) == 1
+    }
+
+    fn set(&mut self, x: isize, y: isize, value: bool) -> bool {
+        let idx = self.idx(x, y);
+        self.cells[idx] = value;
+        if self.well_composed(x, y) {
+            if value { self.count += 1 } else { self.count -= 1 }
+            true
+        } else {
+            self.cells[idx] = !value;
+            false
+        }
+    }
+
+    fn add(&mut self, x: isize, y: isize) -> bool {
+        self.can_add(x, y) && self.set(x, y, true)
+    }
+
+    fn remove(&mut self, x: isize, y: isize) -> bool {
+        self.can_remove(x, y) && self.set(x, y, false)
+    }
+
+    // Adds every cell it can, starting from the ones next to the region
+    fn grow(&mut self) {
+        loop {
+            let before = self.count;
+            let mut queue: VecDeque<Point> = VecDeque::new();
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    if self.get(x, y) { queue.push_back(Point::new(x, y)); }
+                }
+            }
+            while let Some(p) = queue.pop_front() {
+                for (dx, dy) in &NEIGHBOURS {
+                    if self.add(p.x + dx, p.y + dy) {
+                        queue.push_back(Point::new(p.x + dx, p.y + dy));
+                    }
+                }
+            }
+            if self.count == before { break; }
+        }
+    }
+
+    // Marks a path from an excluded square to the outside of the map as
+    // forbidden, so growing around that square can't enclose it in a hole
+    fn channel(&mut self, from: &Point, open: &mut [bool]) -> bool {
+        let idx = self.idx(from.x, from.y);
+        if open[idx] { return true; }
+        let mut prev: Vec<Option<usize>> = vec![None; open.len()];
+        let mut queue: VecDeque<Point> = VecDeque::new();
+        let mut seen = vec![false; open.len()];
+        seen[idx] = true;
+        queue.push_back(*from);
+        while let Some(p) = queue.pop_front() {
+            let p_idx = self.idx(p.x, p.y);
+            let exit = !NEIGHBOURS.iter().all(|(dx, dy)| self.valid(p.x + dx, p.y + dy)) || open[p_idx];
+            if exit {
+                let mut cur = Some(p_idx);
+                while let Some(i) = cur {
+                    self.forbidden[i] = true;
+                    open[i] = true;
+                    cur = prev[i];
+                }
+                return true;
+            }
+            for (dx, dy) in &NEIGHBOURS {
+                let (x, y) = (p.x + dx, p.y + dy);
+                if !self.valid(x, y) { continue; }
+                let i = self.idx(x, y);
+                if seen[i] || self.pinned[i] { continue; }
+                seen[i] = true;
+                prev[i] = Some(p_idx);
+                queue.push_back(Point::new(x, y));
+            }
+        }
+        false
+    }
+
+    // Lattice point (x,y) is a polygon vertex when 1 or 3 of the cells around it are set
+    fn is_vertex(&self, x: isize, y: isize) -> bool {
+        let set = [(-1,-1),(-1,0),(0,-1),(0,0)].iter().filter(|(dx, dy)| self.get(x + dx, y + dy)).count();
+        set == 1 || set == 3
+    }
+
+    fn vertices(&self) -> usize {
+        (0..=self.size).map(|y| (0..=self.size).filter(|&x| self.is_vertex(x, y)).count()).sum()
+    }
+
+    // Change in vertex count if cell (x,y) was flipped
+    fn delta(&mut self, x: isize, y: isize) -> isize {
+        let corners = |r: &Region| [(0,0),(1,0),(0,1),(1,1)].iter().filter(|(dx, dy)| r.is_vertex(x + dx, y + dy)).count() as isize;
+        let before = corners(self);
+        let idx = self.idx(x, y);
+        self.cells[idx] = !self.cells[idx];
+        let after = corners(self);
+        self.cells[idx] = !self.cells[idx];
+        after - before
+    }
+
+    // Flips cells changing the vertex count in the wanted direction until it reaches target
+    fn reshape(&mut self, target: usize, min_count: usize) -> usize {
+        let mut vertices = self.vertices();
+        loop {
+            let before = vertices;
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    if vertices == target { return vertices; }
+                    let delta = self.delta(x, y);
+                    let wanted = if vertices < target { delta > 0 && vertices as isize + delta <= target as isize + 3 } else { delta < 0 };
+                    if !wanted { continue; }
+                    let flipped = if self.get(x, y) { self.count > min_count && self.remove(x, y) } else { self.add(x, y) };
+                    if flipped { vertices = (vertices as isize + delta) as usize; }
+                    if (before < target) != (vertices < target) { return vertices; }
+                }
+            }
+            if vertices == before { return vertices; }
+        }
+    }
+
+    // Polygon vertices, counterclockwise (region on the left)
+    fn contour(&self) -> Vec<Point> {
+        let vertex = |x: isize, y: isize| (x + y * (self.size + 1)) as usize;
+        let mut next: Vec<Option<Point>> = vec![None; ((self.size + 1) * (self.size + 1)) as usize];
+        let mut start = None;
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.get(x, y) { continue; }
+                if !self.get(x, y - 1) { next[vertex(x, y)]         = Some(Point::new(x + 1, y)); start = start.or(Some(Point::new(x, y))); }
+                if !self.get(x + 1, y) { next[vertex(x + 1, y)]     = Some(Point::new(x + 1, y + 1)); }
+                if !self.get(x, y + 1) { next[vertex(x + 1, y + 1)] = Some(Point::new(x, y + 1)); }
+                if !self.get(x - 1, y) { next[vertex(x, y + 1)]     = Some(Point::new(x, y)); }
+            }
+        }
+        let mut points: Vec<Point> = Vec::new();
+        if let Some(start) = start {
+            let mut cur = start;
+            loop {
+                let to = next[vertex(cur.x, cur.y)].unwrap();
+                let after = next[vertex(to.x, to.y)].unwrap();
+                if (to.x - cur.x, to.y - cur.y) != (after.x - to.x, after.y - to.y) {
+                    points.push(to);
+                }
+                cur = to;
+                if cur == start { break; }
+            }
+        }
+        points
+    }
+}
+
+fn format_point(p: &Point) -> String {
+    format!("({},{})", p.x, p.y)
+}
+
+pub fn format_contour(points: &[Point]) -> String {
+    points.iter().map(format_point).collect::<Vec<_>>().join(",")
+}
+
+// Builds a .desc map satisfying cond
+pub fn solve(cond: &Cond) -> Result<String, String> {
+    let size = cond.size;
+    let mut region = Region::new(size);
+    for p in cond.include.iter().chain(cond.exclude.iter()) {
+        if !region.valid(p.x, p.y) { return Err(format!("square {} is outside of the {}x{} map", format_point(p), size, size)); }
+    }
+    for p in &cond.include {
+        let idx = region.idx(p.x, p.y);
+        region.pinned[idx] = true;
+    }
+
+    let mut open = vec![false; (size * size) as usize];
+    let mut exclude = cond.exclude.clone();
+    exclude.sort_by_key(|p| p.x.min(p.y).min(size - 1 - p.x).min(size - 1 - p.y));
+    for p in &exclude {
+        let idx = region.idx(p.x, p.y);
+        if region.pinned[idx] { return Err(format!("square {} is both included and excluded", format_point(p))); }
+        region.forbidden[idx] = true;
+    }
+    for p in &exclude {
+        if !region.channel(p, &mut open) { return Err(format!("no way out of the map from excluded square {}", format_point(p))); }
+    }
+
+    let seed = cond.include.first().cloned().unwrap_or_else(|| Point::new(size / 2, size / 2));
+    if !region.add(seed.x, seed.y) { return Err(format!("can't start the map at {}", format_point(&seed))); }
+    region.grow();
+    if let Some(p) = cond.include.iter().find(|p| !region.get(p.x, p.y)) {
+        return Err(format!("included square {} is unreachable", format_point(p)));
+    }
+
+    let min_count = ((size * size) as usize).div_ceil(5);
+    if region.count < min_count {
+        return Err(format!("map area {} is below 20% of {}x{}", region.count, size, size));
+    }
+    let vertices = region.vertices();
+    if vertices < cond.v_min {
+        region.reshape(cond.v_min, min_count);
+    } else if vertices > cond.v_max {
+        region.reshape(cond.v_max, min_count);
+    }
+    let contour = region.contour();
+    if contour.len() < cond.v_min || contour.len() > cond.v_max {
+        return Err(format!("map has {} vertices, expected {}..{}", contour.len(), cond.v_min, cond.v_max));
+    }
+
+    // start and boosters spread evenly over the map
+    let cells: Vec<Point> = (0..size).flat_map(|y| (0..size).map(move |x| Point::new(x, y)))
+                                     .filter(|p| region.get(p.x, p.y)).collect();
+    let total: usize = cond.boosters.iter().sum::<usize>() + 1;
+    if cells.len() < total { return Err(format!("not enough room for {} boosters", total - 1)); }
+    let mut spots = (0..total).map(|i| cells[i * cells.len() / total]);
+    let start = spots.next().unwrap();
+    let mut boosters: Vec<String> = Vec::new();
+    for (letter, &count) in BOOSTERS.iter().zip(cond.boosters.iter()) {
+        for _ in 0..count {
+            boosters.push(format!("{}{}", letter, format_point(&spots.next().unwrap())));
+        }
+    }
+
+    Ok(format!("{}#{}##{}", format_contour(&contour), format_point(&start), boosters.join(";")))
+}
+
+// Touching or crossing of two axis-aligned edges
+fn touch(a1: &Point, a2: &Point, b1: &Point, b2: &Point) -> bool {
+    let overlap = |a: isize, b: isize, c: isize, d: isize| a.min(b) <= c.max(d) && c.min(d) <= a.max(b);
+    overlap(a1.x, a2.x, b1.x, b2.x) && overlap(a1.y, a2.y, b1.y, b2.y)
+}
+
+// Checks a candidate .desc against cond, returning every violation found
+pub fn check(cond: &Cond, desc: &str) -> Result<(), Vec<PuzzleError>> {
+    let Candidate { contour: points, start, items } = parse_puzzle(desc).map_err(|e| vec![PuzzleError::Malformed(e)])?;
+    let mut errors: Vec<PuzzleError> = Vec::new();
+    let size = cond.size;
+    let n = points.len();
+    let edge = |i: usize| (points[i], points[(i + 1) % n]);
+
+    for p in points.iter().filter(|p| p.x < 0 || p.x > size || p.y < 0 || p.y > size) {
+        errors.push(PuzzleError::OutOfBounds(*p));
+    }
+    for i in 0..n {
+        let (a, b) = edge(i);
+        let (_, c) = edge((i + 1) % n);
+        if (a.x == b.x) == (a.y == b.y) {
+            errors.push(PuzzleError::NotRectilinear(a, b));
+        } else if (a.x == b.x) == (b.x == c.x) && (a.y == b.y) == (b.y == c.y) {
+            errors.push(PuzzleError::Collinear(b));
+        }
+    }
+    if !errors.is_empty() { return Err(errors); }
+
+    'simple: for i in 0..n {
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 { continue; }
+            let (a1, a2) = edge(i);
+            let (b1, b2) = edge(j);
+            if touch(&a1, &a2, &b1, &b2) {
+                errors.push(PuzzleError::NotSimple(a1, b1));
+                break 'simple;
+            }
+        }
+    }
+    if !errors.is_empty() { return Err(errors); }
+
+    let grid = match fill(&contour_walls(&points), size, size) {
+        Ok(grid) => grid,
+        Err(y)   => return Err(vec![PuzzleError::Malformed(ParseError { offset: 0, fragment: Fragment::CONTOUR, reason: Reason::UnpairedWalls(y) })])
+    };
+    let inside = |p: &Point| p.x >= 0 && p.x < size && p.y >= 0 && p.y < size && grid[(p.x + p.y * size) as usize] == Cell::EMPTY;
+
+    if n < cond.v_min || n > cond.v_max {
+        errors.push(PuzzleError::Vertices { count: n, min: cond.v_min, max: cond.v_max });
+    }
+    let area = grid.iter().filter(|&&c| c == Cell::EMPTY).count();
+    let min_area = ((size * size) as usize).div_ceil(5);
+    if area < min_area {
+        errors.push(PuzzleError::Area { area, min: min_area });
+    }
+    for p in cond.include.iter().filter(|p| !inside(p)) {
+        errors.push(PuzzleError::NotIncluded(*p));
+    }
+    for p in cond.exclude.iter().filter(|p| inside(p)) {
+        errors.push(PuzzleError::NotExcluded(*p));
+    }
+    if !inside(&start) {
+        errors.push(PuzzleError::OutsideMap('S', start));
+    }
+    for (letter, p) in items.iter().filter(|(_, p)| !inside(p)) {
+        errors.push(PuzzleError::OutsideMap(*letter, *p));
+    }
+    for (&letter, &expected) in BOOSTERS.iter().zip(cond.boosters.iter()) {
+        let count = items.iter().filter(|(l, _)| *l == letter).count();
+        if count != expected {
+            errors.push(PuzzleError::Boosters { letter, count, expected });
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
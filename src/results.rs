@@ -0,0 +1,117 @@
+use std::fmt;
+use std::collections::BTreeMap;
+
+// Best known solution per problem, kept as results.csv next to the .sol files
+
+pub const HEADER: &str = "problem,score,previous,bought,strategy,options,timestamp";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub problem:   String,        // file name, prob-NNN.desc
+    pub score:     usize,         // validated time steps of the .sol
+    pub previous:  Option<usize>, // best score before this one
+    pub bought:    String,        // purchase the solution was made with, in .buy format
+    pub strategy:  String,
+    pub options:   String,
+    pub timestamp: u64            // unix seconds
+}
+
+impl Record {
+    // Score change against the previous best, negative is better
+    pub fn delta(&self) -> Option<isize> {
+        self.previous.map(|p| self.score as isize - p as isize)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Results {
+    pub records: BTreeMap<String, Record>
+}
+
+fn field<'a>(fields: &mut impl Iterator<Item = &'a str>, line: usize, name: &str) -> Result<&'a str, String> {
+    fields.next().ok_or_else(|| format!("line {}: missing {}", line, name))
+}
+
+fn number<T: std::str::FromStr>(s: &str, line: usize, name: &str) -> Result<T, String> {
+    s.parse::<T>().map_err(|_| format!("line {}: bad {} {:?}", line, name, s))
+}
+
+impl Results {
+    pub fn parse(s: &str) -> Result<Results, String> {
+        let mut records = BTreeMap::new();
+        for (i, text) in s.lines().enumerate() {
+            if text.is_empty() || (i == 0 && text == HEADER) { continue; }
+            let mut fields = text.split(',');
+            let problem   = field(&mut fields, i + 1, "problem")?.to_string();
+            let score     = number(field(&mut fields, i + 1, "score")?, i + 1, "score")?;
+            let previous  = match field(&mut fields, i + 1, "previous")? {
+                ""       => None,
+                previous => Some(number(previous, i + 1, "previous")?)
+            };
+            let bought    = field(&mut fields, i + 1, "bought")?.to_string();
+            let strategy  = field(&mut fields, i + 1, "strategy")?.to_string();
+            let options   = field(&mut fields, i + 1, "options")?.to_string();
+            let timestamp = number(field(&mut fields, i + 1, "timestamp")?, i + 1, "timestamp")?;
+            if fields.next().is_some() { return Err(format!("line {}: too many fields", i + 1)); }
+            records.insert(problem.clone(), Record { problem, score, previous, bought, strategy, options, timestamp });
+        }
+        Ok(Results { records })
+    }
+
+    pub fn insert(&mut self, record: Record) {
+        self.records.insert(record.problem.clone(), record);
+    }
+}
+
+impl fmt::Display for Results {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for r in self.records.values() {
+            let previous = r.previous.map_or(String::new(), |p| p.to_string());
+            writeln!(f, "{},{},{},{},{},{},{}", r.problem, r.score, previous, r.bought, r.strategy, r.options, r.timestamp)?;
+        }
+        Ok(())
+    }
+}
+
+// 2019-06-21 10:00 UTC
+pub fn date(timestamp: u64) -> String {
+    // days to civil date, http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, timestamp % 86400 / 3600, timestamp % 3600 / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut results = Results::default();
+        results.insert(Record { problem: String::from("prob-002.desc"), score: 120, previous: Some(130), bought: String::from("CB"),
+                                strategy: String::from("greedy-zones"), options: String::from("hands=line"), timestamp: 1_561_111_200 });
+        results.insert(Record { problem: String::from("prob-001.desc"), score: 48, previous: None, bought: String::new(),
+                                strategy: String::from("greedy-zones"), options: String::from("hands=lookahead:20"), timestamp: 0 });
+        let text = results.to_string();
+        assert!(text.starts_with(HEADER));
+        assert_eq!(Results::parse(&text).unwrap(), results);
+        assert_eq!(results.records["prob-002.desc"].delta(), Some(-10));
+        assert!(Results::parse("prob-001.desc,x,,,greedy-zones,hands=line,0").is_err());
+        assert!(Results::parse("prob-001.desc,48,,").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01 00:00 UTC");
+        assert_eq!(date(1_561_111_200), "2019-06-21 10:00 UTC");
+        assert_eq!(date(951_782_400), "2000-02-29 00:00 UTC");
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use fnv::FnvHashSet;
+use rand::Rng;
+use crate::{ Point, Bonus, Orientation, Level, Drone, UNDECIDED_ZONE, MAX_ZONES, would_wrap };
+
+// Knobs of the greedy strategy. Mostly how exploration rates a position to move to:
+// rating is divided by plan length to the power of decay, so that closer positions win
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scorer {
+    pub boosters: [f64; 5], // rating of a booster cell, by Bonus
+    pub wall:     f64,      // a wrapped cell counts as max(1, wall * walls around it)
+    pub zone:     f64,      // multiplier for positions outside of the drone's zone
+    pub decay:    f64,
+    pub horizon:  usize,    // exploration looks this many steps further while nothing is found
+    pub wheels:   usize,    // free cells in a line needed to turn wheels on
+    pub drill:    bool,     // whether drills are used at all
+    pub zones:    f64       // zones per drone the map is split in
+}
+
+impl Default for Scorer {
+    fn default() -> Scorer {
+        Scorer { boosters: [100.; 5], wall: 1., zone: 0., decay: 1., horizon: 5, wheels: 4, drill: true, zones: 1. }
+    }
+}
+
+const BOOSTERS: [(char, Bonus); 5] = [('B', Bonus::HAND), ('F', Bonus::WHEELS), ('L', Bonus::DRILL), ('R', Bonus::TELEPORT), ('C', Bonus::CLONE)];
+
+impl Scorer {
+    pub fn booster(&self, bonus: Bonus) -> f64 {
+        self.boosters[bonus as usize]
+    }
+
+    // Drones without a zone wrap anywhere
+    pub(crate) fn rate(&self, level: &Level, drone: &Drone, pos: &Point, orientation: Orientation) -> f64 {
+        let outside = drone.zone != UNDECIDED_ZONE && level.get_zone(pos.x, pos.y) != drone.zone;
+        if outside && self.zone == 0. { return 0.; }
+        let rating = if let Some(&bonus) = level.bonuses.get(pos) { self.booster(bonus) }
+        else {
+            let mut wrapped: FnvHashSet<Point> = FnvHashSet::default();
+            would_wrap(level, drone, pos, orientation, &mut wrapped);
+            wrapped.iter().map(|p| 1.0_f64.max(self.wall * level.weights[level.grid_idx(p.x, p.y)] as f64)).sum()
+        };
+        if outside { rating * self.zone } else { rating }
+    }
+
+    // Zones for a map with this many drones, at least one
+    pub fn zones_count(&self, drones: usize) -> usize {
+        ((self.zones * drones as f64).round() as usize).clamp(1, MAX_ZONES)
+    }
+
+    // Random neighbour for tuning, every knob changes with probability 1/3.
+    // Fractions are rounded to 2 digits to keep .scorer files readable
+    pub fn mutate<R: Rng>(&self, rng: &mut R) -> Scorer {
+        let mut res = *self;
+        let scale = |v: f64, min: f64, max: f64, rng: &mut R| {
+            if rng.gen_bool(1. / 3.) { ((v * rng.gen_range(0.5, 2.)).clamp(min, max) * 100.).round() / 100. } else { v }
+        };
+        for b in 0..res.boosters.len() {
+            res.boosters[b] = scale(res.boosters[b].max(1.), 0., 10000., rng).round();
+        }
+        res.wall  = scale(res.wall.max(0.1), 0., 10., rng);
+        res.decay = scale(res.decay, 0.25, 4., rng);
+        if rng.gen_bool(1. / 3.) {
+            res.zone = if res.zone == 0. { rng.gen_range(5_f64, 50.).round() / 100. }
+                       else if rng.gen_bool(0.5) { 0. }
+                       else { ((res.zone * rng.gen_range(0.5, 2.)).min(1.) * 100.).round() / 100. };
+        }
+        if rng.gen_bool(1. / 3.) { res.horizon = rng.gen_range(2, 11); }
+        if rng.gen_bool(1. / 3.) { res.wheels = rng.gen_range(0, 9); }
+        if rng.gen_bool(1. / 6.) { res.drill = !res.drill; }
+        if rng.gen_bool(1. / 6.) { res.zones = [0.5, 1., 1., 1.5, 2., 3.][rng.gen_range(0, 6)]; }
+        res
+    }
+}
+
+// Config file of key=value pairs, separated by spaces or newlines, # starts a comment:
+//   B=100 F=100 L=100 R=100 C=100  # booster cells
+//   wall=1 zone=0 decay=1
+//   horizon=5 wheels=4 drill=1 zones=1
+// Missing keys keep default values
+pub fn parse_scorer(s: &str) -> Result<Scorer, String> {
+    let mut scorer = Scorer::default();
+    for (i, line) in s.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let line = line.split('=').map(str::trim).collect::<Vec<_>>().join("=");
+        for pair in line.split_whitespace() {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("line {}: expected key=value, got {:?}", i + 1, pair))?;
+            let bad = || format!("line {}: bad value {:?} for {}", i + 1, value, key);
+            let value = value.parse::<f64>().ok().filter(|v| v.is_finite() && *v >= 0.).ok_or_else(bad)?;
+            let integer = Some(value as usize).filter(|_| value.fract() == 0.);
+            match key {
+                "wall"    => scorer.wall = value,
+                "zone"    => scorer.zone = value,
+                "decay"   => scorer.decay = value,
+                "horizon" => scorer.horizon = integer.filter(|&v| v >= 1).ok_or_else(bad)?,
+                "wheels"  => scorer.wheels = integer.ok_or_else(bad)?,
+                "drill"   => scorer.drill = integer.filter(|&v| v <= 1).ok_or_else(bad)? == 1,
+                "zones"   => scorer.zones = Some(value).filter(|&v| v > 0.).ok_or_else(bad)?,
+                _ => match BOOSTERS.iter().find(|(c, _)| key.len() == 1 && key.starts_with(*c)) {
+                    Some(&(_, bonus)) => scorer.boosters[bonus as usize] = value,
+                    None => return Err(format!("line {}: unknown key {:?}", i + 1, key))
+                }
+            }
+        }
+    }
+    Ok(scorer)
+}
+
+// Same format as the config file, on one line
+impl fmt::Display for Scorer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (c, bonus) in BOOSTERS.iter() {
+            write!(f, "{}={} ", c, self.booster(*bonus))?;
+        }
+        write!(f, "wall={} zone={} decay={} horizon={} wheels={} drill={} zones={}", self.wall, self.zone, self.decay, self.horizon, self.wheels, self.drill as usize, self.zones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        assert_eq!(parse_scorer("").unwrap(), Scorer::default());
+        let scorer = parse_scorer("# drills are worth more\nL = 250 C=0\n\nwall=1.5 zone=0.2 # soft zones\ndecay=2").unwrap();
+        assert_eq!(scorer.booster(Bonus::DRILL), 250.);
+        assert_eq!(scorer.booster(Bonus::CLONE), 0.);
+        assert_eq!(scorer.booster(Bonus::HAND), 100.);
+        assert_eq!((scorer.wall, scorer.zone, scorer.decay), (1.5, 0.2, 2.));
+        assert_eq!(parse_scorer(&scorer.to_string()).unwrap(), scorer);
+        assert!(parse_scorer("X=1").is_err());
+        assert!(parse_scorer("wall").is_err());
+        assert!(parse_scorer("decay=-1").is_err());
+        assert!(parse_scorer("zone=abc").is_err());
+        let scorer = parse_scorer("horizon=8 wheels=0 drill=0 zones=1.5").unwrap();
+        assert_eq!((scorer.horizon, scorer.wheels, scorer.drill, scorer.zones), (8, 0, false, 1.5));
+        assert_eq!(scorer.zones_count(2), 3);
+        assert_eq!(parse_scorer(&scorer.to_string()).unwrap(), scorer);
+        assert!(parse_scorer("horizon=0").is_err());
+        assert!(parse_scorer("wheels=2.5").is_err());
+        assert!(parse_scorer("drill=2").is_err());
+        assert!(parse_scorer("zones=0").is_err());
+    }
+
+    #[test]
+    fn mutations() {
+        use rand::SeedableRng;
+        let mut rng = rand_pcg::Pcg32::seed_from_u64(1);
+        let mut scorer = Scorer::default();
+        for _ in 0..1000 {
+            scorer = scorer.mutate(&mut rng);
+            assert_eq!(parse_scorer(&scorer.to_string()).unwrap(), scorer);
+            assert!(scorer.horizon >= 1 && scorer.decay > 0. && scorer.zones > 0. && scorer.zone <= 1.);
+        }
+    }
+}
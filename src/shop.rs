@@ -0,0 +1,93 @@
+use crate::Bonus;
+
+// Booster prices in coins
+pub fn price(bonus: Bonus) -> usize {
+    match bonus {
+        Bonus::HAND     => 1000,
+        Bonus::WHEELS   => 300,
+        Bonus::DRILL    => 700,
+        Bonus::TELEPORT => 1200,
+        Bonus::CLONE    => 2000
+    }
+}
+
+// Purchase sets tried for every problem, in .buy format
+pub const CANDIDATES: [&str; 8] = ["", "B", "F", "L", "C", "CB", "CC", "CCC"];
+
+pub fn cost(bought: &str) -> usize {
+    crate::parser::parse_buy(bought).unwrap().iter().map(|(&bonus, &count)| price(bonus) * count).sum()
+}
+
+// Contest score for a problem: 1000 * log2(X * Y) * best time / our time.
+// Best time is unknown, so solving without purchases stands in for it
+pub fn weight(width: isize, height: isize) -> f64 {
+    1000.0 * ((width * height) as f64).log2()
+}
+
+pub fn estimate(weight: f64, base_steps: usize, steps: usize) -> f64 {
+    weight * base_steps as f64 / steps as f64
+}
+
+// Multiple-choice knapsack: picks one (cost, score) option per problem so that
+// total cost fits in budget and total score is max. Returns chosen option indices
+pub fn allocate(problems: &[Vec<(usize, f64)>], budget: usize) -> Vec<usize> {
+    let unit = problems.iter().flatten().map(|&(cost, _)| cost).filter(|&c| c > 0).fold(0, gcd).max(1);
+    let capacity = budget / unit;
+    // best[c] = max score with total cost of c units at most, choice[i][c] = option taken for problem i
+    let mut best: Vec<f64> = vec![0.0; capacity + 1];
+    let mut choice: Vec<Vec<usize>> = Vec::with_capacity(problems.len());
+    for options in problems {
+        let mut next = vec![f64::NEG_INFINITY; capacity + 1];
+        let mut taken = vec![0; capacity + 1];
+        for (idx, &(cost, score)) in options.iter().enumerate() {
+            let units = cost / unit;
+            for c in units..=capacity {
+                let total = best[c - units] + score;
+                if total > next[c] {
+                    next[c] = total;
+                    taken[c] = idx;
+                }
+            }
+        }
+        best = next;
+        choice.push(taken);
+    }
+
+    let mut c = (0..=capacity).fold(0, |a, b| if best[b] > best[a] { b } else { a });
+    let mut res = vec![0; problems.len()];
+    for (i, options) in problems.iter().enumerate().rev() {
+        res[i] = choice[i][c];
+        c -= options[res[i]].0 / unit;
+    }
+    res
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_of_purchases() {
+        assert_eq!(cost(""), 0);
+        assert_eq!(cost("CCB"), 5000);
+        assert_eq!(cost("FL R\n"), 2200);
+    }
+
+    #[test]
+    fn allocate_within_budget() {
+        let problems = vec![
+            vec![(0, 100.0), (2000, 150.0), (4000, 180.0)],
+            vec![(0, 100.0), (2000, 170.0), (1000, 110.0)],
+            vec![(0, 100.0), (1000, 130.0)]
+        ];
+        assert_eq!(allocate(&problems, 0),     vec![0, 0, 0]);
+        assert_eq!(allocate(&problems, 2000),  vec![0, 1, 0]);
+        assert_eq!(allocate(&problems, 3000),  vec![0, 1, 1]);
+        assert_eq!(allocate(&problems, 5000),  vec![1, 1, 1]);
+        assert_eq!(allocate(&problems, 99999), vec![2, 1, 1]);
+    }
+}
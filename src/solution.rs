@@ -0,0 +1,178 @@
+use std::{cmp, fmt};
+use std::str::FromStr;
+use crate::Point;
+
+// One time step of a drone, as written in .sol files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolAction {
+    UP,               // W
+    RIGHT,            // D
+    DOWN,             // S
+    LEFT,             // A
+    WAIT,             // Z
+    CLOCKWISE,        // E
+    COUNTERCLOCKWISE, // Q
+    WHEELS,           // F
+    DRILL,            // L
+    BEAKON,           // R
+    CLONE,            // C
+    HAND(Point),      // B(dx,dy), offset relative to the drone as it's facing now
+    JUMP(Point)       // T(x,y), position of an installed beakon
+}
+
+impl fmt::Display for SolAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolAction::UP               => write!(f, "W"),
+            SolAction::RIGHT            => write!(f, "D"),
+            SolAction::DOWN             => write!(f, "S"),
+            SolAction::LEFT             => write!(f, "A"),
+            SolAction::WAIT             => write!(f, "Z"),
+            SolAction::CLOCKWISE        => write!(f, "E"),
+            SolAction::COUNTERCLOCKWISE => write!(f, "Q"),
+            SolAction::WHEELS           => write!(f, "F"),
+            SolAction::DRILL            => write!(f, "L"),
+            SolAction::BEAKON           => write!(f, "R"),
+            SolAction::CLONE            => write!(f, "C"),
+            SolAction::HAND(p)          => write!(f, "B({},{})", p.x, p.y),
+            SolAction::JUMP(p)          => write!(f, "T({},{})", p.x, p.y)
+        }
+    }
+}
+
+// Time steps at which a drone was cloned and did its last action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub spawn:  usize,
+    pub finish: usize
+}
+
+// Actions of every drone, in order of cloning. Prints in .sol format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub paths: Vec<Vec<SolAction>>
+}
+
+impl Solution {
+    // Replays the order of actions without a map: a clone made at step t starts
+    // acting at t + 1, clones made in the same step are numbered in drone order.
+    // None for drones that are never cloned
+    pub fn timeline(&self) -> Vec<Option<Timing>> {
+        let mut spawns: Vec<usize> = vec![0];
+        let mut step = 0;
+        while (0..spawns.len()).any(|idx| step < spawns[idx] + self.paths.get(idx).map_or(0, |p| p.len())) {
+            step += 1;
+            for idx in 0..spawns.len() {
+                let path = self.paths.get(idx).map_or(&[][..], |p| &p[..]);
+                if step > spawns[idx] && path.get(step - spawns[idx] - 1) == Some(&SolAction::CLONE) {
+                    spawns.push(step);
+                }
+            }
+        }
+        (0..cmp::max(spawns.len(), self.paths.len())).map(|idx| {
+            spawns.get(idx).map(|&spawn| Timing { spawn, finish: spawn + self.paths.get(idx).map_or(0, |p| p.len()) })
+        }).collect()
+    }
+
+    // Time steps taken until the last drone is done
+    pub fn score(&self) -> usize {
+        self.timeline().iter().flatten().map(|t| t.finish).max().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, path) in self.paths.iter().enumerate() {
+            if idx > 0 { write!(f, "#")?; }
+            for action in path {
+                write!(f, "{}", action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolutionError {
+    pub offset: usize, // bytes from the start of the file
+    pub drone:  usize,
+    pub reason: String
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "drone {} at byte {}: {}", self.drone, self.offset, self.reason)
+    }
+}
+
+// Integer in canonical form only: no plus sign, leading zeros or -0,
+// so that printing it back gives the same bytes
+fn parse_int(s: &[u8], i: &mut usize) -> Option<isize> {
+    let from = *i;
+    if s.get(*i) == Some(&b'-') { *i += 1; }
+    let digits = *i;
+    while s.get(*i).is_some_and(|c| c.is_ascii_digit()) { *i += 1; }
+    let text = std::str::from_utf8(&s[from..*i]).ok()?;
+    let canonical = *i > digits && (s[digits] != b'0' || (*i == digits + 1 && digits == from));
+    if canonical { text.parse::<isize>().ok() } else { None }
+}
+
+// (x,y)
+fn parse_args(s: &[u8], i: &mut usize) -> Option<Point> {
+    if s.get(*i) != Some(&b'(') { return None; }
+    *i += 1;
+    let x = parse_int(s, i)?;
+    if s.get(*i) != Some(&b',') { return None; }
+    *i += 1;
+    let y = parse_int(s, i)?;
+    if s.get(*i) != Some(&b')') { return None; }
+    *i += 1;
+    Some(Point::new(x, y))
+}
+
+// Strict: anything but actions and # between drones is an error, trailing newline included
+pub fn parse_solution(s: &str) -> Result<Solution, SolutionError> {
+    let bytes = s.as_bytes();
+    let mut paths = vec![Vec::new()];
+    let mut i = 0;
+    while i < bytes.len() {
+        let offset = i;
+        let drone = paths.len() - 1;
+        let c = bytes[i];
+        i += 1;
+        let action = match c {
+            b'#' => { paths.push(Vec::new()); continue; }
+            b'W' => SolAction::UP,
+            b'D' => SolAction::RIGHT,
+            b'S' => SolAction::DOWN,
+            b'A' => SolAction::LEFT,
+            b'Z' => SolAction::WAIT,
+            b'E' => SolAction::CLOCKWISE,
+            b'Q' => SolAction::COUNTERCLOCKWISE,
+            b'F' => SolAction::WHEELS,
+            b'L' => SolAction::DRILL,
+            b'R' => SolAction::BEAKON,
+            b'C' => SolAction::CLONE,
+            b'B' | b'T' => {
+                let arg = parse_args(bytes, &mut i).ok_or_else(|| SolutionError {
+                    offset, drone, reason: format!("malformed arguments to {}", c as char)
+                })?;
+                if c == b'B' { SolAction::HAND(arg) } else { SolAction::JUMP(arg) }
+            }
+            _ => {
+                let c = s[offset..].chars().next().unwrap();
+                return Err(SolutionError { offset, drone, reason: format!("unknown action {:?}", c) });
+            }
+        };
+        paths[drone].push(action);
+    }
+    Ok(Solution { paths })
+}
+
+impl FromStr for Solution {
+    type Err = SolutionError;
+
+    fn from_str(s: &str) -> Result<Solution, SolutionError> {
+        parse_solution(s)
+    }
+}
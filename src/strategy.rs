@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use crate::{ Point, Cell, Bonus, Action, Attach, Options, Scorer, Level, Drone, Zone, UNDECIDED_ZONE,
+             get_or, jitter, explore_impl, explore_wrapping, explore_clone, explore_spawn };
+use crate::tour::TourPlanner;
+use crate::sweep::SweepPlanner;
+use crate::beam::BeamPlanner;
+use crate::mcts::MctsStrategy;
+
+// What a drone does in one time step
+#[derive(Clone)]
+pub(crate) enum Decision {
+    CLONE,                        // at a spawn point with a clone collected
+    WHEELS,
+    DRILL,
+    HAND(Point),                  // attach a manipulator, in drone coordinates
+    BEAKON,
+    PLAN(Zone, VecDeque<Action>), // new zone and plan, its first action is taken right away
+    FOLLOW                        // next action of the current plan
+}
+
+// Decides what drones do, one drone at a time in drone order. The solving loop
+// applies decisions, picks up boosters and wears off wheels and drill
+pub(crate) trait Strategy {
+    fn decide(&mut self, level: &Level, drones: &[Drone], idx: usize) -> Decision;
+}
+
+// Where a drone goes next to wrap, once boosters and clones are taken care of
+pub(crate) trait Planner {
+    fn plan(&mut self, level: &Level, drones: &[Drone], idx: usize, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>>;
+}
+
+// Names for --strategy, the first one is the default
+pub const STRATEGIES: [&str; 6] = ["greedy-zones", "greedy", "tour", "sweep", "beam", "mcts"];
+
+pub(crate) fn by_name(options: &Options) -> Box<dyn Strategy> {
+    let (zones, planner): (bool, Box<dyn Planner>) = match options.strategy {
+        "greedy" => (false, Box::new(GreedyPlanner)),
+        "tour"   => (true, Box::new(TourPlanner::default())),
+        "sweep"  => (true, Box::new(SweepPlanner::default())),
+        "beam"   => (true, Box::new(BeamPlanner)),
+        "mcts"   => return Box::new(MctsStrategy::new(options)),
+        _        => (true, Box::new(GreedyPlanner))
+    };
+    Box::new(GreedyZoneStrategy::new(options, options.seed, zones, planner))
+}
+
+// The closest position that wraps the most
+pub(crate) struct GreedyPlanner;
+
+impl Planner for GreedyPlanner {
+    fn plan(&mut self, level: &Level, drones: &[Drone], idx: usize, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+        explore_wrapping(level, &drones[idx], scorer, seed)
+    }
+}
+
+// Every drone takes its own zone and wraps it as the planner says, by default
+// the closest cells in it that wrap the most. Boosters are used as soon as they're
+// collected, the first drone collects clones and brings them to a spawn point.
+// Without zones drones wrap anywhere. Positions to wrap are rated by the scorer
+pub(crate) struct GreedyZoneStrategy {
+    attach:  Attach,
+    scorer:  Scorer,
+    seed:    u64,
+    zones:   bool,
+    planner: Box<dyn Planner>
+}
+
+// New zone and a path into it, once the drone's zone is wrapped
+fn choose_zone(drone: &Drone, taken: &[Zone], level: &Level, scorer: &Scorer, seed: u64) -> Option<(Zone, VecDeque<Action>)> {
+    if drone.zone == UNDECIDED_ZONE || level.zones_empty[drone.zone as usize] == 0 {
+        let not_empty:  Vec<u8> = (0..level.zones_empty.len() as u8).filter(|&z| level.zones_empty[z as usize] > 0).collect();
+        let not_taken:  Vec<u8> = not_empty.iter().cloned().filter(|&z| taken.iter().all(|&t| t != z)).collect();
+        let looking_in: Vec<u8> = if !not_taken.is_empty() { not_taken } else { not_empty };
+        let rate = |level: &Level, _: &Drone, pos: &Point, _| {
+            if level.get_cell(pos.x, pos.y) == Cell::EMPTY && looking_in.contains(&level.get_zone(pos.x, pos.y)) { 1. + jitter(pos, seed) }
+            else { 0. }
+        };
+
+        if let Some((plan, pos, _)) = explore_impl(level, drone, rate, scorer) {
+            Some((level.get_zone(pos.x, pos.y), plan))
+        } else {
+            panic!("No zone left to choose")
+        }
+    } else {
+        None
+    }
+}
+
+impl GreedyZoneStrategy {
+    pub(crate) fn new(options: &Options, seed: u64, zones: bool, planner: Box<dyn Planner>) -> GreedyZoneStrategy {
+        GreedyZoneStrategy { attach: options.attach, scorer: options.scorer, seed, zones, planner }
+    }
+}
+
+impl Strategy for GreedyZoneStrategy {
+    fn decide(&mut self, level: &Level, drones: &[Drone], idx: usize) -> Decision {
+        let drone = &drones[idx];
+        if self.zones {
+            let taken: Vec<Zone> = drones.iter().map(|d| d.zone).collect();
+            if let Some((zone, plan)) = choose_zone(drone, &taken, level, &self.scorer, self.seed) {
+                return Decision::PLAN(zone, plan);
+            }
+        }
+        if drone.can_follow(level) { return Decision::FOLLOW; }
+
+        let has = |bonus| get_or(&level.collected, &bonus, 0) > 0;
+        if has(Bonus::CLONE) && level.spawns.contains(&drone.pos) {
+            Decision::CLONE
+        } else if has(Bonus::WHEELS) && drone.wheels == 0 && drone.has_space(level, self.scorer.wheels as isize) {
+            Decision::WHEELS
+        } else if has(Bonus::DRILL) && drone.drill == 0 && self.scorer.drill {
+            Decision::DRILL
+        } else if has(Bonus::HAND) {
+            Decision::HAND(drone.attach_point(level, self.attach, &self.scorer))
+        } else if has(Bonus::TELEPORT) && level.beakons.iter().all(|b| (b.x - drone.pos.x).abs() + (b.y - drone.pos.y).abs() >= 50) {
+            Decision::BEAKON
+        } else if let Some(plan) = explore_clone(level, drone, idx)
+                                   .or_else(|| explore_spawn(level, drone, idx))
+                                   .or_else(|| self.planner.plan(level, drones, idx, &self.scorer, self.seed)) {
+            Decision::PLAN(drone.zone, plan)
+        } else {
+            // nothing left to go to, a plan that can't be followed is dropped
+            Decision::PLAN(drone.zone, VecDeque::new())
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use fnv::FnvHashSet;
+use crate::{ Point, Cell, Action, Scorer, Level, Drone, Zone, UNDECIDED_ZONE, explore_impl, explore_wrapping };
+use crate::strategy::Planner;
+use crate::tour::{zone_cells, regions, center, distances, order, explore_region};
+
+// Pieces of free space with fewer cells to wrap or narrower on average are wrapped greedily
+const MIN_AREA:  usize = 200;
+const MIN_WIDTH: usize = 15;
+
+// Row the drone rides from one end to the other, wrapping cells around it
+struct Lane {
+    row:     isize,
+    from:    isize,
+    to:      isize,
+    cells:   Vec<Point>,
+    started: bool
+}
+
+enum Part {
+    LANE(Lane),
+    REGION(FnvHashSet<Point>) // cells to wrap greedily
+}
+
+struct Sweep {
+    zone:  Zone,
+    hands: usize,
+    parts: VecDeque<Part>
+}
+
+// Boustrophedon coverage: free space of a drone's zone is cut into pieces with one
+// run of cells per row, and open pieces are swept in lanes along rows, as far apart
+// as the hands reach. Lanes are laid out again when the drone gets a hand
+#[derive(Default)]
+pub(crate) struct SweepPlanner {
+    sweeps: Vec<Option<Sweep>>
+}
+
+fn free(level: &Level, zone: Zone, x: isize, y: isize) -> bool {
+    level.in_map(x, y) && level.walkable(x, y) && (zone == UNDECIDED_ZONE || level.get_zone(x, y) == zone)
+}
+
+// Runs of free cells in a row
+fn runs(level: &Level, zone: Zone, y: isize) -> Vec<(isize, isize)> {
+    let mut res = Vec::new();
+    let mut x = 0;
+    while x < level.width {
+        if free(level, zone, x, y) {
+            let from = x;
+            while x + 1 < level.width && free(level, zone, x + 1, y) { x += 1; }
+            res.push((from, x));
+        }
+        x += 1;
+    }
+    res
+}
+
+fn overlap(a: (isize, isize), b: (isize, isize)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1
+}
+
+// Pieces with one run per row, rows in order. A run continues the piece below
+// when the two only overlap each other, otherwise a new piece starts
+fn decompose(level: &Level, zone: Zone) -> Vec<Vec<(isize, isize, isize)>> {
+    let mut pieces: Vec<Vec<(isize, isize, isize)>> = Vec::new();
+    let mut below: Vec<((isize, isize), usize)> = Vec::new();
+    for y in 0..level.height {
+        let row = runs(level, zone, y);
+        let mut current = Vec::with_capacity(row.len());
+        for &run in &row {
+            let under: Vec<&((isize, isize), usize)> = below.iter().filter(|(b, _)| overlap(*b, run)).collect();
+            let piece = match under.as_slice() {
+                [(b, piece)] if row.iter().filter(|r| overlap(**r, *b)).count() == 1 => *piece,
+                _ => { pieces.push(Vec::new()); pieces.len() - 1 }
+            };
+            pieces[piece].push((y, run.0, run.1));
+            current.push((run, piece));
+        }
+        below = current;
+    }
+    pieces
+}
+
+// Offsets the hands reach along an axis, as the drone is facing now
+fn reach(drone: &Drone, axis: fn(&Point) -> isize) -> (isize, isize) {
+    let offsets = drone.hands.iter().map(|h| axis(&drone.orientation.rotate(h)));
+    (offsets.clone().min().unwrap(), offsets.max().unwrap())
+}
+
+// Lanes over a piece, from the bottom or the top and starting at the left or the right end
+fn lanes(piece: &[(isize, isize, isize)], (lo, hi): (isize, isize), up: bool, right: bool) -> Vec<Lane> {
+    let (y0, y1) = (piece[0].0, piece[piece.len() - 1].0);
+    let run = |y: isize| piece[(y - y0) as usize];
+    let mut res = Vec::new();
+    let mut covered = if up { y0 } else { y1 };
+    while (y0..=y1).contains(&covered) {
+        let row = if up { covered - lo } else { covered - hi }.clamp(y0, y1);
+        let (_, xa, xb) = run(row);
+        let (from, to) = if right == (res.len() % 2 == 0) { (xb, xa) } else { (xa, xb) };
+        let cells = (row + lo..=row + hi).filter(|y| (y0..=y1).contains(y))
+            .flat_map(|y| { let (_, a, b) = run(y); (a.max(xa)..=b.min(xb)).map(move |x| Point::new(x, y)) }).collect();
+        res.push(Lane { row, from, to, cells, started: false });
+        covered = if up { row + hi + 1 } else { row + lo - 1 };
+    }
+    res
+}
+
+// Pieces are swept if they're open enough, the other cells are split into regions
+// wrapped greedily. Both are visited in the order of a short tour
+fn plan_sweep(level: &Level, drone: &Drone) -> Sweep {
+    let to_wrap = |&(y, a, b): &(isize, isize, isize)| (a..=b).filter(|&x| level.get_cell(x, y) == Cell::EMPTY).count();
+    let open = |p: &Vec<(isize, isize, isize)>| {
+        let width = p.iter().map(|(_, a, b)| (b - a + 1) as usize).sum::<usize>();
+        p.iter().map(to_wrap).sum::<usize>() >= MIN_AREA && width >= MIN_WIDTH * p.len()
+    };
+    let pieces: Vec<_> = decompose(level, drone.zone).into_iter().filter(open).collect();
+    let swept: FnvHashSet<Point> = pieces.iter().flatten().flat_map(|&(y, a, b)| (a..=b).map(move |x| Point::new(x, y))).collect();
+    let regions = regions(zone_cells(level, drone.zone).into_iter().filter(|p| !swept.contains(p)).collect());
+
+    let mut points = vec![drone.pos];
+    points.extend(pieces.iter().map(|p| center(&p.iter().map(|&(y, a, b)| Point::new((a + b) / 2, y)).collect::<Vec<_>>())));
+    points.extend(regions.iter().map(|r| center(r)));
+    let tour = order(&distances(level, &points));
+    let reach = reach(drone, |p| p.y);
+    let mut sweep = Sweep { zone: drone.zone, hands: drone.hands.len(), parts: VecDeque::new() };
+    let mut pos = drone.pos;
+    for &k in &tour[1..] {
+        if k > pieces.len() {
+            pos = points[k];
+            sweep.parts.push_back(Part::REGION(regions[k - pieces.len() - 1].iter().cloned().collect()));
+        } else {
+            let options = [(true, true), (true, false), (false, true), (false, false)];
+            let best = options.iter().map(|&(up, right)| lanes(&pieces[k - 1], reach, up, right))
+                .min_by_key(|l| (l[0].from - pos.x).abs() + (l[0].row - pos.y).abs()).unwrap();
+            let last = &best[best.len() - 1];
+            pos = Point::new(last.to, last.row);
+            sweep.parts.extend(best.into_iter().map(Part::LANE));
+        }
+    }
+    sweep
+}
+
+impl Planner for SweepPlanner {
+    fn plan(&mut self, level: &Level, drones: &[Drone], idx: usize, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+        let drone = &drones[idx];
+        if self.sweeps.len() <= idx { self.sweeps.resize_with(idx + 1, || None); }
+        let sweep = &mut self.sweeps[idx];
+        if sweep.as_ref().is_none_or(|s| s.zone != drone.zone || s.hands != drone.hands.len()) {
+            *sweep = Some(plan_sweep(level, drone));
+        }
+        let parts = &mut sweep.as_mut().unwrap().parts;
+        let reach_x = reach(drone, |p| p.x);
+        while let Some(part) = parts.front_mut() {
+            let lane = match part {
+                Part::LANE(lane) => lane,
+                Part::REGION(region) => {
+                    if region.iter().all(|p| level.get_cell(p.x, p.y) != Cell::EMPTY) { parts.pop_front(); continue; }
+                    return explore_region(level, drone, region, scorer, seed);
+                }
+            };
+            // ends of the lane are trimmed to what's left to wrap, as far as hands reach
+            let xs: Vec<isize> = lane.cells.iter().filter(|p| level.get_cell(p.x, p.y) == Cell::EMPTY).map(|p| p.x).collect();
+            let (min, max) = match (xs.iter().min(), xs.iter().max()) {
+                (Some(&min), Some(&max)) => (min - reach_x.1, max - reach_x.0),
+                _ => { parts.pop_front(); continue; }
+            };
+            let (from, to) = if lane.from <= lane.to { (lane.from.max(min), lane.to.min(max)) } else { (lane.from.min(max), lane.to.max(min)) };
+            let target = Point::new(if lane.started { to } else { from }, lane.row);
+            let passed = if lane.from <= lane.to { drone.pos.x >= to } else { drone.pos.x <= to };
+            if (lane.started && passed) || (drone.pos == target && from == to) {
+                parts.pop_front();
+            } else if drone.pos == target {
+                lane.started = true;
+            } else {
+                let rate = |_: &Level, _: &Drone, pos: &Point, _| if *pos == target { 1. } else { 0. };
+                match explore_impl(level, drone, rate, scorer) {
+                    Some((plan, _, _)) => return Some(plan),
+                    None => { parts.pop_front(); }
+                }
+            }
+        }
+        explore_wrapping(level, drone, scorer, seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_level;
+
+    #[test]
+    fn sweep_pieces() {
+        // an obstacle in the middle splits the map into the rows below it, either side of it and above it
+        let problem = parse_level("(0,0),(10,0),(10,6),(0,6)#(0,0)#(3,2),(6,2),(6,4),(3,4)#").unwrap();
+        let pieces = decompose(&problem.level, UNDECIDED_ZONE);
+        assert_eq!(pieces, vec![vec![(0, 0, 9), (1, 0, 9)], vec![(2, 0, 2), (3, 0, 2)], vec![(2, 6, 9), (3, 6, 9)],
+                                vec![(4, 0, 9), (5, 0, 9)]]);
+
+        // hands reach a row either side, one lane wraps two rows
+        let lanes = lanes(&pieces[0], reach(&problem.drones[0], |p| p.y), true, true);
+        assert_eq!(lanes.len(), 1);
+        assert_eq!((lanes[0].row, lanes[0].from, lanes[0].to, lanes[0].cells.len()), (1, 9, 0, 20));
+        let lanes = super::lanes(&[(0, 0, 9), (1, 0, 9), (2, 0, 9), (3, 0, 9), (4, 0, 9)], (-1, 1), false, false);
+        assert_eq!(lanes.iter().map(|l| (l.row, l.from, l.to)).collect::<Vec<_>>(), vec![(3, 0, 9), (0, 9, 0)]);
+    }
+}
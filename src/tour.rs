@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use fnv::FnvHashSet;
+use crate::{ Point, Cell, Action, Scorer, Level, Drone, Zone, UNDECIDED_ZONE, jitter, would_wrap, explore_impl, explore_wrapping };
+use crate::strategy::Planner;
+
+// Regions a zone is split into, roughly
+const REGIONS: usize = 200;
+const MIN_TILE: isize = 4;
+// Positions that wrap the current region are rated this many times higher
+const FOCUS: f64 = 16.;
+const UNREACHABLE: i64 = 1 << 40;
+
+// Unwrapped cells of a zone, in regions to visit one after another
+struct Tour {
+    zone:    Zone,
+    regions: VecDeque<FnvHashSet<Point>>
+}
+
+// Plans a short tour over the regions of a drone's zone whenever it takes a zone,
+// then wraps greedily, preferring the current region until it's done. Regions are connected
+// unwrapped cells within square tiles, so pockets behind walls are regions of their own
+#[derive(Default)]
+pub(crate) struct TourPlanner {
+    tours: Vec<Option<Tour>>
+}
+
+fn to_wrap(level: &Level, zone: Zone, x: isize, y: isize) -> bool {
+    level.valid(x, y) && level.get_cell(x, y) == Cell::EMPTY && (zone == UNDECIDED_ZONE || level.get_zone(x, y) == zone)
+}
+
+// Unwrapped cells of a zone, row by row
+pub(crate) fn zone_cells(level: &Level, zone: Zone) -> Vec<Point> {
+    (0..level.height).flat_map(|y| (0..level.width).map(move |x| Point::new(x, y)))
+        .filter(|p| to_wrap(level, zone, p.x, p.y)).collect()
+}
+
+pub(crate) fn regions(cells: Vec<Point>) -> Vec<Vec<Point>> {
+    let to_wrap: FnvHashSet<Point> = cells.iter().cloned().collect();
+    let tile = ((cells.len() as f64 / REGIONS as f64).sqrt().ceil() as isize).max(MIN_TILE);
+    let mut seen: FnvHashSet<Point> = FnvHashSet::default();
+    let mut res = Vec::new();
+    for cell in cells {
+        if !seen.insert(cell) { continue; }
+        let mut region = vec![cell];
+        let mut i = 0;
+        while i < region.len() {
+            let p = region[i];
+            for (dx, dy) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                let n = Point::new(p.x + dx, p.y + dy);
+                if n.x.div_euclid(tile) == cell.x.div_euclid(tile) && n.y.div_euclid(tile) == cell.y.div_euclid(tile)
+                   && to_wrap.contains(&n) && seen.insert(n) {
+                    region.push(n);
+                }
+            }
+            i += 1;
+        }
+        res.push(region);
+    }
+    res
+}
+
+// Cell of the region closest to its middle
+pub(crate) fn center(region: &[Point]) -> Point {
+    let (sx, sy) = region.iter().fold((0, 0), |(sx, sy), p| (sx + p.x, sy + p.y));
+    let (mx, my) = (sx / region.len() as isize, sy / region.len() as isize);
+    *region.iter().min_by_key(|p| (p.x - mx).pow(2) + (p.y - my).pow(2)).unwrap()
+}
+
+// Walking distances between every pair of points
+pub(crate) fn distances(level: &Level, points: &[Point]) -> Vec<Vec<i64>> {
+    let mut res = Vec::with_capacity(points.len());
+    let mut dist = vec![UNREACHABLE; (level.width * level.height) as usize];
+    let mut queue: VecDeque<Point> = VecDeque::new();
+    for from in points {
+        for d in dist.iter_mut() { *d = UNREACHABLE; }
+        dist[level.grid_idx(from.x, from.y)] = 0;
+        queue.push_back(*from);
+        while let Some(p) = queue.pop_front() {
+            let d = dist[level.grid_idx(p.x, p.y)];
+            for (dx, dy) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                let n = Point::new(p.x + dx, p.y + dy);
+                if level.in_map(n.x, n.y) && level.walkable(n.x, n.y) && dist[level.grid_idx(n.x, n.y)] == UNREACHABLE {
+                    dist[level.grid_idx(n.x, n.y)] = d + 1;
+                    queue.push_back(n);
+                }
+            }
+        }
+        res.push(points.iter().map(|p| dist[level.grid_idx(p.x, p.y)]).collect());
+    }
+    res
+}
+
+// Distance between a and b, nothing if b is past the end of an open tour
+fn leg(d: &[Vec<i64>], a: usize, b: Option<usize>) -> i64 {
+    b.map_or(0, |b| d[a][b])
+}
+
+// Open tour from node 0 through all the others: nearest insertion, then
+// 2-opt and Or-opt moves while they make it shorter
+pub(crate) fn order(d: &[Vec<i64>]) -> Vec<usize> {
+    let n = d.len();
+    let mut tour = vec![0];
+    let mut nearest = d[0].clone();
+    let mut in_tour = vec![false; n];
+    in_tour[0] = true;
+    while tour.len() < n {
+        let k = (0..n).filter(|&k| !in_tour[k]).min_by_key(|&k| nearest[k]).unwrap();
+        let p = (1..=tour.len()).min_by_key(|&p| d[tour[p - 1]][k] + leg(d, k, tour.get(p).copied()) - leg(d, tour[p - 1], tour.get(p).copied())).unwrap();
+        tour.insert(p, k);
+        in_tour[k] = true;
+        for j in 0..n { nearest[j] = nearest[j].min(d[k][j]); }
+    }
+    improve(&mut tour, d);
+    tour
+}
+
+fn improve(tour: &mut Vec<usize>, d: &[Vec<i64>]) {
+    let n = tour.len();
+    loop {
+        let mut improved = false;
+        // 2-opt: reverse tour[i..=j]
+        for i in 1..n {
+            for j in i + 1..n {
+                let (a, b, c, e) = (tour[i - 1], tour[i], tour[j], tour.get(j + 1).copied());
+                if d[a][c] + leg(d, b, e) < d[a][b] + leg(d, c, e) {
+                    tour[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+        // Or-opt: move up to 3 nodes in a row elsewhere, either way round
+        for len in 1..=3 {
+            let mut i = 1;
+            while i + len <= tour.len() {
+                let segment = tour[i..i + len].to_vec();
+                let (a, e) = (tour[i - 1], tour.get(i + len).copied());
+                let removed = d[a][segment[0]] + leg(d, segment[len - 1], e) - leg(d, a, e);
+                let mut rest = tour.clone();
+                rest.drain(i..i + len);
+                let mut best: Option<(i64, usize, bool)> = None;
+                for p in 1..=rest.len() {
+                    let (x, y) = (rest[p - 1], rest.get(p).copied());
+                    for &reversed in &[false, true] {
+                        let (first, last) = if reversed { (segment[len - 1], segment[0]) } else { (segment[0], segment[len - 1]) };
+                        let added = d[x][first] + leg(d, last, y) - leg(d, x, y);
+                        if added < removed && best.is_none_or(|(b, _, _)| added < b) {
+                            best = Some((added, p, reversed));
+                        }
+                    }
+                }
+                if let Some((_, p, reversed)) = best {
+                    let mut segment = segment;
+                    if reversed { segment.reverse(); }
+                    rest.splice(p..p, segment);
+                    *tour = rest;
+                    improved = true;
+                }
+                i += 1;
+            }
+        }
+        if !improved { break; }
+    }
+}
+
+// Greedy exploration, preferring positions that wrap cells of the region
+pub(crate) fn explore_region(level: &Level, drone: &Drone, region: &FnvHashSet<Point>, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+    let rate = |level: &Level, drone: &Drone, pos: &Point, orientation| {
+        let mut wrapped: FnvHashSet<Point> = FnvHashSet::default();
+        would_wrap(level, drone, pos, orientation, &mut wrapped);
+        let focus = if region.contains(pos) || wrapped.iter().any(|p| region.contains(p)) { 1. } else { 1. / FOCUS };
+        scorer.rate(level, drone, pos, orientation) * (1. + jitter(pos, seed)) * focus
+    };
+    explore_impl(level, drone, rate, scorer).map(|(plan, _, _)| plan)
+        .or_else(|| explore_wrapping(level, drone, scorer, seed))
+}
+
+fn plan_tour(level: &Level, drone: &Drone) -> Tour {
+    let regions = regions(zone_cells(level, drone.zone));
+    let mut points = vec![drone.pos];
+    points.extend(regions.iter().map(|r| center(r)));
+    let d = distances(level, &points);
+    let tour = order(&d);
+    Tour { zone: drone.zone, regions: tour[1..].iter().map(|&k| regions[k - 1].iter().cloned().collect()).collect() }
+}
+
+impl Planner for TourPlanner {
+    fn plan(&mut self, level: &Level, drones: &[Drone], idx: usize, scorer: &Scorer, seed: u64) -> Option<VecDeque<Action>> {
+        let drone = &drones[idx];
+        if self.tours.len() <= idx { self.tours.resize_with(idx + 1, || None); }
+        let tour = &mut self.tours[idx];
+        if tour.as_ref().is_none_or(|t| t.zone != drone.zone) {
+            *tour = Some(plan_tour(level, drone));
+        }
+        let regions = &mut tour.as_mut().unwrap().regions;
+        while regions.front().is_some_and(|r| r.iter().all(|p| level.get_cell(p.x, p.y) != Cell::EMPTY)) {
+            regions.pop_front();
+        }
+        match regions.front() {
+            Some(region) => explore_region(level, drone, region, scorer, seed),
+            None         => explore_wrapping(level, drone, scorer, seed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(tour: &[usize], d: &[Vec<i64>]) -> i64 {
+        tour.windows(2).map(|w| d[w[0]][w[1]]).sum()
+    }
+
+    #[test]
+    fn tour_order() {
+        // points on a line, 0 in the middle: going one way first then back is shortest
+        let xs: [i64; 6] = [0, 5, -1, 3, -4, 1];
+        let d: Vec<Vec<i64>> = xs.iter().map(|a| xs.iter().map(|b| (a - b).abs()).collect()).collect();
+        let tour = order(&d);
+        assert_eq!(tour[0], 0);
+        assert_eq!(length(&tour, &d), 13);
+        let mut sorted = tour.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..xs.len()).collect::<Vec<_>>());
+    }
+}
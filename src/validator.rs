@@ -0,0 +1,212 @@
+use std::fmt;
+use fnv::{FnvHashMap};
+use crate::{ Point, Cell, Bonus, Drone, Level, SolAction, Solution, is_reaching };
+
+#[derive(Debug, Clone)]
+pub struct Invalid {
+    pub drone:  Option<usize>,
+    pub step:   usize,
+    pub reason: String
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.drone {
+            Some(drone) => write!(f, "drone {} step {}: {}", drone, self.step, self.reason),
+            None        => write!(f, "step {}: {}", self.step, self.reason)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub level:  Level,
+    pub drones: Vec<Drone>,
+    pub step:   usize,
+    // Cells and boosters with the step they were wrapped, drilled or picked up at
+    pub wrapped:   Vec<(Point, usize)>,
+    pub drilled:   Vec<(Point, usize)>,
+    pub picked_up: Vec<(Point, usize)>,
+    commands:   Vec<Vec<SolAction>>,
+    cursors:    Vec<usize>,
+    picked:     FnvHashMap<Bonus, usize>
+}
+
+impl Replay {
+    pub fn new(level: Level, drones: Vec<Drone>, solution: &Solution) -> Replay {
+        let commands = solution.paths.clone();
+        let mut replay = Replay { level, drones, commands, step: 0, cursors: Vec::new(), picked: FnvHashMap::default(),
+                                  wrapped: Vec::new(), drilled: Vec::new(), picked_up: Vec::new() };
+        for idx in 0..replay.drones.len() {
+            replay.cursors.push(0);
+            replay.arrive(idx);
+        }
+        replay.restock();
+        replay
+    }
+
+    pub fn done(&self) -> bool {
+        (0..self.drones.len()).all(|idx| self.cursors[idx] >= self.commands.get(idx).map_or(0, |c| c.len()))
+    }
+
+    fn invalid(&self, drone: usize, reason: String) -> Invalid {
+        Invalid { drone: Some(drone), step: self.step, reason }
+    }
+
+    fn wrap(&mut self, idx: usize) {
+        let drone = &self.drones[idx];
+        let mut to_wrap = Vec::new();
+        for hand in &drone.hands {
+            let offset = drone.orientation.rotate(hand);
+            let p = Point::new(drone.pos.x + offset.x, drone.pos.y + offset.y);
+            if self.level.valid(p.x, p.y) && self.level.get_cell(p.x, p.y) == Cell::EMPTY && is_reaching(&self.level, &drone.pos, &offset) {
+                to_wrap.push(p);
+            }
+        }
+        for p in to_wrap {
+            self.level.wrap_cell(p.x, p.y);
+            self.wrapped.push((p, self.step));
+        }
+    }
+
+    fn arrive(&mut self, idx: usize) {
+        let pos = self.drones[idx].pos;
+        if let Some(bonus) = self.level.bonuses.remove(&pos) {
+            *self.picked.entry(bonus).or_insert(0) += 1;
+            self.picked_up.push((pos, self.step));
+        }
+        self.wrap(idx);
+    }
+
+    fn restock(&mut self) {
+        for (bonus, count) in self.picked.drain() {
+            *self.level.collected.entry(bonus).or_insert(0) += count;
+        }
+    }
+
+    fn spend(&mut self, idx: usize, bonus: Bonus) -> Result<(), Invalid> {
+        match self.level.collected.get_mut(&bonus) {
+            Some(count) if *count > 0 => { *count -= 1; Ok(()) }
+            _ => Err(self.invalid(idx, format!("no {:?} booster available", bonus)))
+        }
+    }
+
+    fn go(&mut self, idx: usize, dx: isize, dy: isize) -> Result<(), Invalid> {
+        let fast = self.drones[idx].wheels > 0;
+        let drill = self.drones[idx].drill > 0;
+        for i in 0..(if fast { 2 } else { 1 }) {
+            let pos = self.drones[idx].pos;
+            let to = Point::new(pos.x + dx, pos.y + dy);
+            if !self.level.in_map(to.x, to.y) || (!drill && !self.level.walkable(to.x, to.y)) {
+                if i == 0 {
+                    return Err(self.invalid(idx, format!("cannot move from ({},{}) to ({},{})", pos.x, pos.y, to.x, to.y)));
+                }
+                break;
+            }
+            if self.level.get_cell(to.x, to.y) == Cell::BLOCKED {
+                self.level.drill_cell(to.x, to.y);
+                self.drilled.push((to, self.step));
+            }
+            self.drones[idx].pos = to;
+            self.arrive(idx);
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, idx: usize, command: SolAction) -> Result<(), Invalid> {
+        match command {
+            SolAction::UP    => self.go(idx,  0,  1)?,
+            SolAction::RIGHT => self.go(idx,  1,  0)?,
+            SolAction::DOWN  => self.go(idx,  0, -1)?,
+            SolAction::LEFT  => self.go(idx, -1,  0)?,
+            SolAction::WAIT  => {},
+            SolAction::CLOCKWISE => {
+                self.drones[idx].orientation = self.drones[idx].orientation.clockwise();
+                self.wrap(idx);
+            }
+            SolAction::COUNTERCLOCKWISE => {
+                self.drones[idx].orientation = self.drones[idx].orientation.counterclockwise();
+                self.wrap(idx);
+            }
+            SolAction::WHEELS => {
+                self.spend(idx, Bonus::WHEELS)?;
+                self.drones[idx].wheels = 51;
+            }
+            SolAction::DRILL => {
+                self.spend(idx, Bonus::DRILL)?;
+                self.drones[idx].drill = 31;
+            }
+            SolAction::HAND(offset) => {
+                let hand = self.drones[idx].orientation.unrotate(&offset);
+                let hands = &self.drones[idx].hands;
+                if hands.contains(&hand) {
+                    return Err(self.invalid(idx, format!("manipulator ({},{}) already attached", offset.x, offset.y)));
+                }
+                if !hands.iter().any(|h| (h.x - hand.x).abs() + (h.y - hand.y).abs() == 1) {
+                    return Err(self.invalid(idx, format!("manipulator ({},{}) is not adjacent to the body or other manipulators", offset.x, offset.y)));
+                }
+                self.spend(idx, Bonus::HAND)?;
+                self.drones[idx].hands.push(hand);
+                self.wrap(idx);
+            }
+            SolAction::BEAKON => {
+                let pos = self.drones[idx].pos;
+                if let Some(b) = self.level.beakons.iter().find(|b| (b.x - pos.x).abs() + (b.y - pos.y).abs() < 50) {
+                    return Err(self.invalid(idx, format!("beakon at ({},{}) is too close to the one at ({},{})", pos.x, pos.y, b.x, b.y)));
+                }
+                self.spend(idx, Bonus::TELEPORT)?;
+                self.level.beakons.push(pos);
+            }
+            SolAction::JUMP(to) => {
+                if !self.level.beakons.contains(&to) {
+                    return Err(self.invalid(idx, format!("no beakon installed at ({},{})", to.x, to.y)));
+                }
+                self.drones[idx].pos = to;
+                self.arrive(idx);
+            }
+            SolAction::CLONE => {
+                let pos = self.drones[idx].pos;
+                if !self.level.spawns.contains(&pos) {
+                    return Err(self.invalid(idx, format!("({},{}) is not a spawn point", pos.x, pos.y)));
+                }
+                self.spend(idx, Bonus::CLONE)?;
+                self.drones.push(Drone::new(pos));
+                self.cursors.push(0);
+            }
+        }
+        Ok(())
+    }
+
+    // Advances the whole swarm by one time step; boosters picked up during it
+    // become usable only from the next one
+    pub fn advance(&mut self) -> Result<(), Invalid> {
+        self.step += 1;
+        for idx in 0..self.drones.len() {
+            let cursor = self.cursors[idx];
+            if let Some(&command) = self.commands.get(idx).and_then(|c| c.get(cursor)) {
+                self.cursors[idx] += 1;
+                self.drones[idx].wear_off();
+                self.execute(idx, command)?;
+            }
+        }
+        self.restock();
+        Ok(())
+    }
+
+    pub fn run(mut self) -> Result<usize, Invalid> {
+        while !self.done() {
+            self.advance()?;
+        }
+        if self.commands.len() > self.drones.len() {
+            return Err(Invalid { drone: Some(self.drones.len()), step: self.step, reason: String::from("drone was never cloned") });
+        }
+        if self.level.empty > 0 {
+            return Err(Invalid { drone: None, step: self.step, reason: format!("{} cells left unwrapped", self.level.empty) });
+        }
+        Ok(self.step)
+    }
+}
+
+pub fn validate(level: Level, drones: Vec<Drone>, solution: &Solution) -> Result<usize, Invalid> {
+    Replay::new(level, drones, solution).run()
+}
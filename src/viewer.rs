@@ -0,0 +1,179 @@
+use std::cmp::{min, max};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::{ Problem, Solution, DELAY, print_level };
+use crate::validator::{ Replay, Invalid };
+
+// Replay states are kept every so many steps, stepping back replays from the nearest one
+const CHECKPOINT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key { CHAR(char), LEFT, RIGHT, ENTER, BACKSPACE }
+
+fn stty(args: &[&str]) -> Option<String> {
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty").args(args).stdin(Stdio::from(tty)).output().ok()?;
+    if output.status.success() { String::from_utf8(output.stdout).ok() } else { None }
+}
+
+// Key presses read from the terminal on a separate thread
+fn keys() -> io::Result<Receiver<Key>> {
+    let tty = File::open("/dev/tty")?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = io::BufReader::new(tty).bytes().map_while(Result::ok);
+        while let Some(byte) = bytes.next() {
+            let key = match byte {
+                0x1B => match (bytes.next(), bytes.next()) {
+                    (Some(b'['), Some(b'C')) => Key::RIGHT,
+                    (Some(b'['), Some(b'D')) => Key::LEFT,
+                    _ => continue
+                },
+                b'\n' | b'\r' => Key::ENTER,
+                0x7F | 0x08   => Key::BACKSPACE,
+                _             => Key::CHAR(byte as char)
+            };
+            if tx.send(key).is_err() { break; }
+        }
+    });
+    Ok(rx)
+}
+
+struct Viewer {
+    checkpoints: Vec<Replay>,
+    current:     Replay,
+    total:       usize,
+    error:       Option<Invalid>,
+    follow:      usize,
+    delay:       u64,
+    playing:     bool,
+    input:       String
+}
+
+impl Viewer {
+    fn forward(&mut self) -> bool {
+        if self.current.done() || self.error.is_some() { return false; }
+        match self.current.advance() {
+            Ok(()) => {
+                if self.current.step == self.checkpoints.len() * CHECKPOINT {
+                    self.checkpoints.push(self.current.clone());
+                }
+                true
+            }
+            Err(invalid) => {
+                // the failed step is half-done, go back to the state before it
+                let step = self.current.step - 1;
+                self.rewind(step);
+                while self.current.step < step { self.current.advance().unwrap(); }
+                self.error = Some(invalid);
+                false
+            }
+        }
+    }
+
+    fn rewind(&mut self, step: usize) {
+        self.current = self.checkpoints[min(step / CHECKPOINT, self.checkpoints.len() - 1)].clone();
+        self.error = None;
+    }
+
+    fn seek(&mut self, step: usize) {
+        if step < self.current.step { self.rewind(step); }
+        while self.current.step < step && self.forward() {}
+    }
+
+    fn render(&self) {
+        let drones = &self.current.drones;
+        let follow = min(self.follow, drones.len() - 1);
+        print!("\x1B[H\x1B[2J");
+        print_level(&self.current.level, drones, follow);
+        let drone = &drones[follow];
+        println!("step {}/{} \tfollowing drone {} of {} at ({},{}) \t{} every {} ms \tcollected {:?}",
+                 self.current.step, self.total, follow, drones.len(), drone.pos.x, drone.pos.y,
+                 if self.playing { "playing" } else { "paused" }, self.delay, self.current.level.collected);
+        if let Some(invalid) = &self.error {
+            println!("\x1B[38;5;196minvalid: {}\x1B[0m", invalid);
+        }
+        println!("space play/pause  ←/→ step  N⏎ jump to step N  +/- speed  [/] drone  q quit  {}",
+                 if self.input.is_empty() { String::new() } else { format!("jump to {}", self.input) });
+        io::stdout().flush().unwrap();
+    }
+
+    fn run(&mut self, keys: Receiver<Key>) {
+        loop {
+            self.render();
+            let key = if self.playing {
+                match keys.recv_timeout(Duration::from_millis(self.delay)) {
+                    Ok(key) => Some(key),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break
+                }
+            } else {
+                match keys.recv() {
+                    Ok(key) => Some(key),
+                    Err(_)  => break
+                }
+            };
+            match key {
+                None => { self.playing = self.forward(); }
+                Some(Key::CHAR(' ')) => { self.playing = !self.playing; }
+                Some(Key::RIGHT) => {
+                    self.playing = false;
+                    self.forward();
+                }
+                Some(Key::LEFT) => {
+                    self.playing = false;
+                    let step = self.current.step.saturating_sub(1);
+                    self.seek(step);
+                }
+                Some(Key::CHAR(c)) if c.is_ascii_digit() => { self.input.push(c); }
+                Some(Key::BACKSPACE) => { self.input.pop(); }
+                Some(Key::ENTER) => {
+                    if let Ok(step) = self.input.parse::<usize>() {
+                        self.playing = false;
+                        self.seek(step);
+                    }
+                    self.input.clear();
+                }
+                Some(Key::CHAR('+')) | Some(Key::CHAR('=')) => { self.delay = max(self.delay / 2, 1); }
+                Some(Key::CHAR('-')) => { self.delay = min(self.delay * 2, 2000); }
+                Some(Key::CHAR(']')) => { self.follow = (min(self.follow, self.current.drones.len() - 1) + 1) % self.current.drones.len(); }
+                Some(Key::CHAR('[')) => {
+                    let len = self.current.drones.len();
+                    self.follow = (min(self.follow, len - 1) + len - 1) % len;
+                }
+                Some(Key::CHAR('q')) => break,
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+// Steps through a solution in the terminal, same picture as --interactive
+pub fn replay(problem: &Problem, solution: &Solution) -> Result<(), String> {
+    let keys = keys().map_err(|e| format!("no terminal: {}", e))?;
+    let saved = stty(&["-g"]).ok_or("stty failed")?;
+    stty(&["-icanon", "-echo", "min", "1"]).ok_or("stty failed")?;
+    print!("\x1B[?1049h");
+
+    let start = Replay::new(problem.level.clone(), problem.drones.clone(), solution);
+    let mut viewer = Viewer {
+        checkpoints: vec![start.clone()],
+        current:     start,
+        total:       solution.score(),
+        error:       None,
+        follow:      0,
+        delay:       DELAY,
+        playing:     false,
+        input:       String::new()
+    };
+    viewer.run(keys);
+
+    print!("\x1B[?1049l");
+    io::stdout().flush().unwrap();
+    stty(&[saved.trim()]);
+    Ok(())
+}
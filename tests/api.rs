@@ -0,0 +1,245 @@
+use std::fs;
+use std::time::Duration;
+use icfpc2019::{Options, Attach, Point, Invalid, STRATEGIES, parse_scorer, ParseError, Fragment, Reason, parse_level, parse_level_with, parse_solution, solve, solve_restarts, validate, optimize};
+
+const SMALL: &str = "(0,0),(6,0),(6,1),(8,1),(8,2),(6,2),(6,3),(0,3)#(0,0)##";
+const BOOSTERS: &str = "(0,0),(10,0),(10,10),(0,10)#(0,0)#(4,2),(6,2),(6,7),(4,7)#X(0,9);C(9,0);B(9,9);F(2,5);L(8,5)";
+
+fn problem(name: &str) -> String {
+    fs::read_to_string(format!("{}/problems/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+#[test]
+fn parse_small() {
+    let problem = parse_level(SMALL).unwrap();
+    assert_eq!((problem.width(), problem.height()), (8, 3));
+    assert_eq!(problem.area(), 20);
+}
+
+fn parse_error(desc: &str) -> (usize, Fragment, Reason) {
+    match parse_level(desc) {
+        Err(ParseError { offset, fragment, reason }) => (offset, fragment, reason),
+        Ok(_) => panic!("parsed {:?}", desc)
+    }
+}
+
+#[test]
+fn parse_incomplete() {
+    assert_eq!(parse_error("(0,0),(1,0),(1,1),(0,1)#(0,0)"), (29, Fragment::OBSTACLES, Reason::Fragments(2)));
+    assert_eq!(parse_error("(0,0),(1,0),(1,1),(0,1)#(0,0)###"), (31, Fragment::BOOSTERS, Reason::Fragments(5)));
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(parse_error("(0,0),(1,0),(1,1),(0,1)#(0,0##"), (28, Fragment::START, Reason::Expected(")")));
+    assert_eq!(parse_error("(0,0),(1,0),(1,1),(0,1)#(0,0)(1,1)##"), (29, Fragment::START, Reason::Expected("end of start position")));
+    assert_eq!(parse_error("(0,0),(1,0),(1,x),(0,1)#(0,0)##"), (15, Fragment::CONTOUR, Reason::Expected("non-negative number")));
+    assert_eq!(parse_error("(0,0),(1,0),(1,-1),(0,1)#(0,0)##"), (15, Fragment::CONTOUR, Reason::Expected("non-negative number")));
+    assert_eq!(parse_error("(0,0),(1,0),(1,1)#(0,0)##"), (0, Fragment::CONTOUR, Reason::Vertices(3)));
+    assert_eq!(parse_error("(0,0),(2,0),(1,1),(0,1)#(0,0)##"), (6, Fragment::CONTOUR, Reason::NotRectilinear(Point::new(2, 0), Point::new(1, 1))));
+    assert_eq!(parse_error("(0,0),(2,0),(2,2),(0,2)#(3,0)##"), (24, Fragment::START, Reason::Outside(Point::new(3, 0))));
+    assert_eq!(parse_error("(0,0),(2,0),(2,2),(0,2)#(0,0)#(1,1),(3,1),(3,2),(1,2)#"), (36, Fragment::OBSTACLES, Reason::Outside(Point::new(3, 1))));
+    assert_eq!(parse_error("(0,0),(0,1),(0,0),(0,1)#(0,0)##"), (0, Fragment::CONTOUR, Reason::UnpairedWalls(0)));
+    assert_eq!(parse_error("(0,0),(2,0),(2,2),(0,2)#(0,0)##B(1,1);Y(0,1)"), (38, Fragment::BOOSTERS, Reason::UnknownBooster('Y')));
+    assert_eq!(parse_error("(0,0),(2,0),(2,2),(0,2)#(0,0)##B(1,1),F(0,1)"), (37, Fragment::BOOSTERS, Reason::Expected(";")));
+    assert_eq!(parse_error("(0,0),(2,0),(2,2),(0,2)#(0,0)##B(2,1)"), (31, Fragment::BOOSTERS, Reason::Outside(Point::new(2, 1))));
+}
+
+#[test]
+fn parse_bad_purchase() {
+    let error = parse_level_with(SMALL, "CQ").err().unwrap();
+    assert_eq!((error.offset, error.fragment, error.reason), (1, Fragment::BOUGHT, Reason::UnknownBooster('Q')));
+}
+
+#[test]
+fn solve_validates() {
+    for desc in &[SMALL, BOOSTERS] {
+        let problem = parse_level(desc).unwrap();
+        let solution = solve(&problem, &Options::default());
+        assert_eq!(validate(&problem, &solution).unwrap(), solution.score());
+    }
+}
+
+#[test]
+fn solve_is_repeatable() {
+    let problem = parse_level(BOOSTERS).unwrap();
+    assert_eq!(solve(&problem, &Options::default()), solve(&problem, &Options::default()));
+}
+
+#[test]
+fn solve_with_attach_policies() {
+    let problem = parse_level(&problem("prob-010.desc")).unwrap();
+    for attach in &[Attach::LINE, Attach::SYMMETRIC, Attach::TSHAPE, Attach::LOOKAHEAD(10)] {
+        let options = Options { attach: *attach, ..Options::default() };
+        let solution = solve(&problem, &options);
+        assert_eq!(validate(&problem, &solution).unwrap(), solution.score(), "{:?}", attach);
+    }
+}
+
+#[test]
+fn solve_with_strategies() {
+    for desc in &[BOOSTERS.to_string(), problem("prob-010.desc")] {
+        let problem = parse_level(desc).unwrap();
+        for strategy in STRATEGIES.iter() {
+            let solution = solve(&problem, &Options { strategy, budget: Some(Duration::from_millis(500)), ..Options::default() });
+            assert_eq!(validate(&problem, &solution).unwrap(), solution.score(), "{}", strategy);
+        }
+    }
+}
+
+#[test]
+fn mcts_starts_greedy() {
+    // without time to search, drones follow the first rollout, which is greedy
+    let problem = parse_level(&problem("prob-005.desc")).unwrap();
+    let options = |strategy| Options { strategy, budget: Some(Duration::from_secs(0)), ..Options::default() };
+    assert_eq!(solve(&problem, &options("mcts")), solve(&problem, &options("greedy")));
+}
+
+#[test]
+fn mcts_with_clones() {
+    // clones made during a time step only act from the next one, in the tree too
+    let problem = parse_level(BOOSTERS).unwrap();
+    for seed in 1..=6 {
+        let solution = solve(&problem, &Options { strategy: "mcts", seed, budget: Some(Duration::from_millis(300)), ..Options::default() });
+        assert_eq!(validate(&problem, &solution).unwrap(), solution.score(), "seed {}", seed);
+        assert_eq!(solution.paths.len(), 2, "seed {}", seed);
+    }
+}
+
+#[test]
+fn solve_with_scorer() {
+    let problem = parse_level(&problem("prob-010.desc")).unwrap();
+    let default = solve(&problem, &Options::default());
+    let scorer = parse_scorer("wall=1 decay=1").unwrap();
+    assert_eq!(solve(&problem, &Options { scorer, ..Options::default() }), default);
+    for config in &["B=300 wall=2 zone=0.5 decay=1.5", "horizon=2 wheels=0 drill=0 zones=3", "zones=0.5"] {
+        let scorer = parse_scorer(config).unwrap();
+        let solution = solve(&problem, &Options { scorer, ..Options::default() });
+        assert_eq!(validate(&problem, &solution).unwrap(), solution.score(), "{}", config);
+    }
+}
+
+#[test]
+fn solve_with_restarts() {
+    let problem = parse_level(&problem("prob-010.desc")).unwrap();
+    let seeded = solve(&problem, &Options { seed: 3, ..Options::default() });
+    assert_eq!(validate(&problem, &seeded).unwrap(), seeded.score());
+    let restarts = solve_restarts(&problem, &Options::default(), Duration::from_millis(300));
+    assert!(!restarts.steps.is_empty());
+    let (seed, best) = restarts.best.unwrap();
+    assert_eq!(Some(best.score()), restarts.steps.iter().flatten().cloned().min());
+    assert_eq!(restarts.steps[seed as usize], Some(best.score()));
+    assert_eq!(restarts.steps[0], Some(solve(&problem, &Options::default()).score()));
+}
+
+#[test]
+fn optimize_detours() {
+    let problem = parse_level(&problem("prob-010.desc")).unwrap();
+    let solution = solve(&problem, &Options::default());
+    let mut detour = solution.clone();
+    detour.paths[0].splice(0..0, parse_solution("DADA").unwrap().paths[0].iter().cloned());
+    assert_eq!(validate(&problem, &detour).unwrap(), solution.score() + 4);
+    let optimized = optimize(&problem, &detour).unwrap().unwrap();
+    assert!(optimized.score() < detour.score());
+    assert_eq!(validate(&problem, &optimized).unwrap(), optimized.score());
+    assert!(optimize(&problem, &parse_solution("D").unwrap()).is_err());
+
+    // a straight corridor can't be wrapped any faster
+    let corridor = parse_level("(0,0),(6,0),(6,1),(0,1)#(0,0)##").unwrap();
+    assert_eq!(optimize(&corridor, &parse_solution("DDDD").unwrap()).unwrap(), None);
+}
+
+#[test]
+fn bought_clones() {
+    let desc = problem("prob-010.desc");
+    let plain = parse_level(&desc).unwrap();
+    let bought = parse_level_with(&desc, "CC").unwrap();
+    let solution = solve(&bought, &Options::default());
+    assert_eq!(solution.paths.len(), 3);
+    assert_eq!(validate(&bought, &solution).unwrap(), solution.score());
+    assert!(validate(&plain, &solution).is_err());
+}
+
+#[test]
+fn bought_more_clones_than_zones() {
+    // zones stop at 253, written 'ƣ', short of the id that marks cells without one
+    let problem = parse_level_with("(0,0),(20,0),(20,20),(0,20)#(0,0)##", &"C".repeat(300)).unwrap();
+    let html = icfpc2019::export::html(&problem, &parse_solution("").unwrap());
+    assert!(html.contains('ƣ') && !html.contains('Ƥ'));
+    let solution = solve(&problem, &Options::default());
+    assert_eq!(validate(&problem, &solution).unwrap(), solution.score());
+}
+
+fn invalid(desc: &str, bought: &str, solution: &str) -> (Option<usize>, usize, String) {
+    let problem = parse_level_with(desc, bought).unwrap();
+    match validate(&problem, &parse_solution(solution).unwrap()) {
+        Err(Invalid { drone, step, reason }) => (drone, step, reason),
+        Ok(steps) => panic!("{:?} took {} steps", solution, steps)
+    }
+}
+
+#[test]
+fn invalid_solution() {
+    assert_eq!(invalid(SMALL, "", "DDDD"), (None, 4, String::from("9 cells left unwrapped")));
+    assert_eq!(invalid(SMALL, "", "DDDDDDDD"), (Some(0), 6, String::from("cannot move from (5,0) to (6,0)")));
+    assert_eq!(invalid(SMALL, "", "DDDDDF"), (Some(0), 6, String::from("no WHEELS booster available")));
+}
+
+#[test]
+fn invalid_boosters() {
+    // the clone can't use wheels picked up by drone 0 in the same step
+    let desc = "(0,0),(6,0),(6,1),(0,1)#(0,0)##X(0,0);F(1,0)";
+    assert_eq!(validate(&parse_level_with(desc, "C").unwrap(), &parse_solution("CDDDD#ZF").unwrap()).unwrap(), 5);
+    assert_eq!(invalid(desc, "C", "CDDDD#F"), (Some(1), 2, String::from("no WHEELS booster available")));
+
+    let corridor = "(0,0),(6,0),(6,1),(0,1)#(0,0)##X(5,0)";
+    assert_eq!(invalid(corridor, "RR", "RDR"), (Some(0), 3, String::from("beakon at (1,0) is too close to the one at (0,0)")));
+    assert_eq!(invalid(corridor, "C", "C"), (Some(0), 1, String::from("(0,0) is not a spawn point")));
+    assert_eq!(invalid(corridor, "B", "B(3,0)"), (Some(0), 1, String::from("manipulator (3,0) is not adjacent to the body or other manipulators")));
+}
+
+#[test]
+fn invalid_timing() {
+    // wheels are on for the 50 steps after F: the 51st is a single cell
+    let desc = "(0,0),(3,0),(3,1),(0,1)#(0,0)##";
+    let wheels = |n| format!("F{}DD", "DA".repeat(n));
+    assert_eq!(validate(&parse_level_with(desc, "F").unwrap(), &parse_solution(&wheels(25)).unwrap()).unwrap(), 53);
+    assert_eq!(invalid(desc, "F", &wheels(24)), (Some(0), 51, String::from("cannot move from (2,0) to (3,0)")));
+
+    // drill is on for the 30 steps after L
+    let desc = "(0,0),(4,0),(4,1),(0,1)#(0,0)#(2,0),(3,0),(3,1),(2,1)#";
+    let drill = |n| format!("DL{}D", "Z".repeat(n));
+    assert_eq!(validate(&parse_level_with(desc, "L").unwrap(), &parse_solution(&drill(29)).unwrap()).unwrap(), 32);
+    assert_eq!(invalid(desc, "L", &drill(30)), (Some(0), 33, String::from("cannot move from (1,0) to (2,0)")));
+}
+
+#[test]
+fn turning_hands() {
+    // the middle of a 3x3 room sees all of it by turning the arm around
+    let room = "(0,0),(3,0),(3,3),(0,3)#(1,1)##";
+    for turns in &["EEE", "QQQ"] {
+        assert_eq!(validate(&parse_level(room).unwrap(), &parse_solution(turns).unwrap()).unwrap(), 3, "{}", turns);
+    }
+    assert_eq!(invalid(room, "", "EE"), (None, 2, String::from("1 cells left unwrapped")));
+
+    // B offsets are on the level: facing down, the arm grows down the corridor
+    let corridor = "(0,0),(1,0),(1,4),(0,4)#(0,3)##";
+    assert_eq!(validate(&parse_level_with(corridor, "BB").unwrap(), &parse_solution("EB(0,-2)B(0,-3)").unwrap()).unwrap(), 3);
+    assert_eq!(invalid(corridor, "BB", "B(0,-2)"), (Some(0), 1, String::from("manipulator (0,-2) is not adjacent to the body or other manipulators")));
+}
+
+#[test]
+fn export_pictures() {
+    let problem = parse_level(BOOSTERS).unwrap();
+    let solution = solve(&problem, &Options::default());
+    let svg = icfpc2019::export::svg(&problem, &solution);
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<path ").count(), solution.paths.len());
+    let html = icfpc2019::export::html(&problem, &solution);
+    assert!(html.contains(&format!("max=\"{}\"", solution.score())));
+
+    // a zone per bought clone, past 'Z' and past what fits in a byte after 'A'
+    let problem = parse_level_with("(0,0),(20,0),(20,20),(0,20)#(0,0)##", &"C".repeat(200)).unwrap();
+    let html = icfpc2019::export::html(&problem, &parse_solution("").unwrap());
+    assert!(html.contains('Z') && html.contains('À') && !html.contains('\\'));
+}
@@ -0,0 +1,93 @@
+use icfpc2019::{Point, ParseError, Fragment, Reason, parse_level};
+use icfpc2019::puzzle::{PuzzleError, parse_cond, solve, check};
+
+const PARAMS: &str = "1,1,10,4,20,1,2,0,0,1,1";
+
+fn cond_error(file: &str) -> (usize, Fragment, Reason) {
+    match parse_cond(file) {
+        Err(ParseError { offset, fragment, reason }) => (offset, fragment, reason),
+        Ok(_) => panic!("parsed {:?}", file)
+    }
+}
+
+#[test]
+fn parse_conds() {
+    let cond = parse_cond("1,2,30,4,20,1,2,3,4,5,6#(1,1),(8,8)#(5,0)\n").unwrap();
+    assert_eq!((cond.block, cond.epoch, cond.size, cond.v_min, cond.v_max), (1, 2, 30, 4, 20));
+    assert_eq!(cond.boosters, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(cond.include, vec![Point::new(1, 1), Point::new(8, 8)]);
+    assert_eq!(cond.exclude, vec![Point::new(5, 0)]);
+    assert!(parse_cond(&format!("{}##", PARAMS)).unwrap().include.is_empty());
+}
+
+#[test]
+fn parse_cond_errors() {
+    assert_eq!(cond_error("1,1,10,4,20,1,2,0,0,1#(1,1)#"), (21, Fragment::PARAMS, Reason::Expected(",")));
+    assert_eq!(cond_error("1,1,10,4,20,1,2,0,0,1,1,1#(1,1)#"), (23, Fragment::PARAMS, Reason::Expected("end of parameters")));
+    assert_eq!(cond_error("1,1,x,4,20,1,2,0,0,1,1#(1,1)#"), (4, Fragment::PARAMS, Reason::Expected("non-negative number")));
+    assert_eq!(cond_error(&format!("{}#(1,1)", PARAMS)), (29, Fragment::EXCLUDE, Reason::Expected("#")));
+    assert_eq!(cond_error(PARAMS), (23, Fragment::INCLUDE, Reason::Expected("#")));
+    assert_eq!(cond_error(&format!("{}#(1,1)#(2,2)#", PARAMS)), (35, Fragment::EXCLUDE, Reason::Expected("end of file")));
+    assert_eq!(cond_error(&format!("{}#(1,1);(2,2)#", PARAMS)), (29, Fragment::INCLUDE, Reason::Expected("end of squares")));
+    assert_eq!(cond_error(&format!("{}#(1,1)#(2,-2)", PARAMS)), (33, Fragment::EXCLUDE, Reason::Expected("non-negative number")));
+}
+
+#[test]
+fn puzzles_parse_back() {
+    for file in &[format!("{}#(1,1),(8,8)#(5,0),(0,5)", PARAMS),
+                  String::from("1,1,30,20,40,2,1,1,1,2,1#(3,3),(20,25),(28,1)#(15,15),(0,29),(10,0)")] {
+        let cond = parse_cond(file).unwrap();
+        let desc = solve(&cond).unwrap();
+        let problem = parse_level(&desc).unwrap_or_else(|e| panic!("{}: {}", desc, e));
+        assert!(problem.width() <= cond.size && problem.height() <= cond.size, "{}", desc);
+        assert!(check(&cond, &desc).is_ok(), "{}", desc);
+    }
+}
+
+// Size 10, 4 to 6 vertices, one B, (1,1) inside and (8,8) outside
+const COND: &str = "1,1,10,4,6,1,0,0,0,0,0#(1,1)#(8,8)";
+
+fn violations(desc: &str) -> Vec<PuzzleError> {
+    check(&parse_cond(COND).unwrap(), desc).err().unwrap_or_else(|| panic!("valid {:?}", desc))
+}
+
+#[test]
+fn check_puzzles() {
+    assert!(check(&parse_cond(COND).unwrap(), "(0,0),(8,0),(8,8),(0,8)#(0,0)##B(2,2)").is_ok());
+    assert_eq!(violations("(0,0),(11,0),(11,8),(0,8)#(0,0)##B(2,2)"),
+               vec![PuzzleError::OutOfBounds(Point::new(11, 0)), PuzzleError::OutOfBounds(Point::new(11, 8))]);
+    assert_eq!(violations("(0,0),(8,0),(8,4),(6,4),(6,6),(4,6),(4,8),(0,8)#(0,0)##B(2,2)"),
+               vec![PuzzleError::Vertices { count: 8, min: 4, max: 6 }]);
+    assert_eq!(violations("(0,0),(4,0),(4,4),(0,4)#(0,0)##B(2,2)"),
+               vec![PuzzleError::Area { area: 16, min: 20 }]);
+    assert_eq!(violations("(2,0),(10,0),(10,10),(2,10)#(2,2)##B(3,3)"),
+               vec![PuzzleError::NotIncluded(Point::new(1, 1)), PuzzleError::NotExcluded(Point::new(8, 8))]);
+    assert_eq!(violations("(0,0),(8,0),(8,8),(0,8)#(0,0)##F(2,2)"),
+               vec![PuzzleError::Boosters { letter: 'B', count: 0, expected: 1 }, PuzzleError::Boosters { letter: 'F', count: 1, expected: 0 }]);
+    assert_eq!(violations("(0,0),(8,0),(8,8),(0,8)#(9,0)##B(9,9)"),
+               vec![PuzzleError::OutsideMap('S', Point::new(9, 0)), PuzzleError::OutsideMap('B', Point::new(9, 9))]);
+}
+
+fn malformed(desc: &str, offset: usize, fragment: Fragment, reason: Reason) {
+    assert_eq!(violations(desc), vec![PuzzleError::Malformed(ParseError { offset, fragment, reason })], "{}", desc);
+}
+
+#[test]
+fn check_malformed() {
+    malformed("(0,0),(8,0),(8,8),(0,8)#(0,0)#", 30, Fragment::BOOSTERS, Reason::Fragments(3));
+    malformed("(0,0),(8,0),(8,8)#(0,0)##B(2,2)", 0, Fragment::CONTOUR, Reason::Vertices(3));
+    malformed("(0,0),(8,0),(8,8),(0,8)(1,2)#(0,0)##B(2,2)", 23, Fragment::CONTOUR, Reason::Expected("end of contour"));
+    malformed("(0,0),(8,0),(8,8),(0,8)#(99999999999999999999,0)##B(2,2)", 25, Fragment::START, Reason::Expected("non-negative number"));
+    malformed("(0,0),(8,0),(8,8),(0,8)#(0,0)#(2,2),(3,2),(3,3),(2,3)#B(1,1)", 30, Fragment::OBSTACLES, Reason::Expected("no obstacles"));
+    malformed("(0,0),(8,0),(8,8),(0,8)#(0,0)##Q(2,2)", 31, Fragment::BOOSTERS, Reason::UnknownBooster('Q'));
+    malformed("(0,0),(8,0),(8,8),(0,8)#(0,0)##B(2,2)(3", 37, Fragment::BOOSTERS, Reason::Expected(";"));
+}
+
+#[test]
+fn check_shapes() {
+    assert_eq!(violations("(0,0),(8,0),(8,8),(1,7)#(0,0)##B(2,2)"),
+               vec![PuzzleError::NotRectilinear(Point::new(8, 8), Point::new(1, 7)), PuzzleError::NotRectilinear(Point::new(1, 7), Point::new(0, 0))]);
+    assert_eq!(violations("(0,0),(8,0),(8,8),(0,8),(0,4)#(0,0)##B(2,2)"), vec![PuzzleError::Collinear(Point::new(0, 4))]);
+    assert_eq!(violations("(0,0),(6,0),(6,4),(2,4),(2,2),(4,2),(4,6),(0,6)#(0,0)##B(1,1)"),
+               vec![PuzzleError::NotSimple(Point::new(6, 4), Point::new(4, 2))]);
+}
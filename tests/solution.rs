@@ -0,0 +1,73 @@
+use std::fs;
+use icfpc2019::{Options, Point, SolAction, Solution, Timing, parse_level, parse_solution, solve, validate};
+
+fn round_trip(s: &str) {
+    let solution = parse_solution(s).unwrap_or_else(|e| panic!("{:?}: {}", s, e));
+    assert_eq!(solution.to_string(), s);
+    assert_eq!(s.parse::<Solution>().unwrap(), solution);
+}
+
+fn error(s: &str) -> (usize, usize) {
+    let error = parse_solution(s).err().unwrap_or_else(|| panic!("parsed {:?}", s));
+    (error.drone, error.offset)
+}
+
+#[test]
+fn parse_actions() {
+    let solution = parse_solution("WDSAZEQFLRCB(1,-2)T(3,40)#B(-1,0)#").unwrap();
+    assert_eq!(solution.paths, vec![
+        vec![SolAction::UP, SolAction::RIGHT, SolAction::DOWN, SolAction::LEFT, SolAction::WAIT,
+             SolAction::CLOCKWISE, SolAction::COUNTERCLOCKWISE, SolAction::WHEELS, SolAction::DRILL,
+             SolAction::BEAKON, SolAction::CLONE, SolAction::HAND(Point::new(1, -2)), SolAction::JUMP(Point::new(3, 40))],
+        vec![SolAction::HAND(Point::new(-1, 0))],
+        vec![]
+    ]);
+    assert_eq!(solution.score(), 13);
+}
+
+#[test]
+fn round_trips() {
+    for s in &["", "W", "#", "WW#DD", "CC#WB(0,0)#B(10,-10)Z", "T(0,0)T(120,7)#R#"] {
+        round_trip(s);
+    }
+}
+
+#[test]
+fn rejects_non_canonical() {
+    assert_eq!(error("WX"), (0, 1));
+    assert_eq!(error("W#DD\n"), (1, 4));
+    assert_eq!(error("W B(1,1)"), (0, 1));
+    assert_eq!(error("B(01,1)"), (0, 0));
+    assert_eq!(error("B(1,-0)"), (0, 0));
+    assert_eq!(error("B(+1,1)"), (0, 0));
+    assert_eq!(error("B(1, 1)"), (0, 0));
+    assert_eq!(error("B(1,1"), (0, 0));
+    assert_eq!(error("W#T(1)"), (1, 2));
+    assert_eq!(error("W#WЖ"), (1, 3));
+}
+
+fn timeline(s: &str) -> Vec<Option<(usize, usize)>> {
+    parse_solution(s).unwrap().timeline().iter().map(|t| t.map(|Timing { spawn, finish }| (spawn, finish))).collect()
+}
+
+#[test]
+fn timelines() {
+    assert_eq!(timeline(""), vec![Some((0, 0))]);
+    assert_eq!(timeline("WDD"), vec![Some((0, 3))]);
+    assert_eq!(timeline("CWW#WWWW"), vec![Some((0, 3)), Some((1, 5))]);
+    assert_eq!(timeline("WC#C#W"), vec![Some((0, 2)), Some((2, 3)), Some((3, 4))]);
+    assert_eq!(timeline("CC#C#W#W"), vec![Some((0, 2)), Some((1, 2)), Some((2, 3)), Some((2, 3))]);
+    assert_eq!(timeline("W#W"), vec![Some((0, 1)), None]);
+    assert_eq!(parse_solution("CWW#WWWW").unwrap().score(), 5);
+}
+
+#[test]
+fn solver_output_round_trips() {
+    for name in &["prob-002.desc", "prob-010.desc", "prob-228.desc"] {
+        let desc = fs::read_to_string(format!("{}/problems/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap();
+        let problem = parse_level(&desc).unwrap();
+        let solution = solve(&problem, &Options::default());
+        round_trip(&solution.to_string());
+        assert_eq!(validate(&problem, &solution).unwrap(), solution.score());
+    }
+}